@@ -3,15 +3,27 @@
 use futures::compat::*;
 use futures::prelude::*;
 use log::{debug, error, warn};
+#[cfg(feature = "tls")]
+use native_tls::{Certificate, TlsConnector};
 use serde::de::DeserializeOwned;
 use std::convert::Into;
 use std::io;
+#[cfg(unix)]
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio::prelude::{Sink, Stream};
 use tokio_tcp::TcpStream;
 use vehicle_information_service::api_type::*;
+use websocket::client::r#async::Client;
+#[cfg(feature = "tls")]
+use websocket::client::r#async::TlsStream;
 use websocket::{ClientBuilder, OwnedMessage, WebSocketError};
 
+#[cfg(feature = "tls")]
+pub use native_tls::Identity;
+
 pub use vehicle_information_service::api_error::ActionErrorResponse;
 pub use vehicle_information_service::api_type::{ActionPath, ReqID, SubscriptionID};
 
@@ -21,6 +33,8 @@ pub enum VISClientError {
     SerdeError(serde_json::Error),
     IoError(io::Error),
     UrlParseError(url::ParseError),
+    #[cfg(feature = "tls")]
+    TlsError(native_tls::Error),
     VisError(ActionErrorResponse),
     Other,
 }
@@ -49,6 +63,13 @@ impl From<url::ParseError> for VISClientError {
     }
 }
 
+#[cfg(feature = "tls")]
+impl From<native_tls::Error> for VISClientError {
+    fn from(tls_error: native_tls::Error) -> Self {
+        VISClientError::TlsError(tls_error)
+    }
+}
+
 impl From<ActionErrorResponse> for VISClientError {
     fn from(action_error: ActionErrorResponse) -> Self {
         VISClientError::VisError(action_error)
@@ -57,10 +78,25 @@ impl From<ActionErrorResponse> for VISClientError {
 
 type Result<T> = core::result::Result<T, VISClientError>;
 
-pub struct VISClient {
+///
+/// Event of a subscription stream.
+///
+#[derive(Debug)]
+pub enum SubscriptionEvent<T> {
+    /// New value of the subscribed signal
+    Value(SubscriptionID, T),
+    /// Notifications were missed, e.g. because the server dropped them for a slow client
+    Gap {
+        subscription_id: SubscriptionID,
+        /// Number of missed notifications
+        missed: u64,
+    },
+}
+
+pub struct VISClient<S = TcpStream> {
     #[allow(dead_code)]
     server_address: String,
-    client: websocket::client::r#async::Client<TcpStream>,
+    client: Client<S>,
 }
 
 impl VISClient {
@@ -76,7 +112,66 @@ impl VISClient {
             client,
         })
     }
+}
+
+#[cfg(feature = "tls")]
+impl VISClient<TlsStream<TcpStream>> {
+    ///
+    /// Connect to a `wss://` server.
+    /// The server certificate is verified against the PEM encoded certificates of the `ca_bundle` only,
+    /// the `identity` is presented to servers that require a client certificate.
+    ///
+    pub async fn connect_secure(
+        server_address: &str,
+        ca_bundle: &[u8],
+        identity: Option<Identity>,
+    ) -> Result<Self> {
+        let mut tls = TlsConnector::builder();
+        tls.disable_built_in_roots(true);
+        for certificate in Certificate::stack_from_pem(ca_bundle)? {
+            tls.add_root_certificate(certificate);
+        }
+        if let Some(identity) = identity {
+            tls.identity(identity);
+        }
+
+        let (client, _headers) = ClientBuilder::new(server_address)?
+            .async_connect_secure(Some(tls.build()?))
+            .compat()
+            .await?;
+        debug!("Connected to: {}", server_address);
+        Ok(Self {
+            server_address: server_address.to_string(),
+            client,
+        })
+    }
+}
+
+#[cfg(unix)]
+impl VISClient<UnixStream> {
+    ///
+    /// Connect to a server listening on the Unix domain socket at `path`, see the `uds` module of the server.
+    ///
+    pub async fn connect_uds<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let server_address = path.as_ref().display().to_string();
+        let stream = UnixStream::connect(path).compat().await?;
+        // The host of the URL is only used for the handshake headers
+        let (client, _headers) = ClientBuilder::new("ws://localhost/")?
+            .async_connect_on(stream)
+            .compat()
+            .await?;
+        debug!("Connected to: {}", server_address);
+        Ok(Self {
+            server_address,
+            client,
+        })
+    }
+}
 
+impl<S> VISClient<S>
+where
+    S: websocket::stream::r#async::Stream + 'static,
+{
     /// Retrieve vehicle signals.
     pub async fn get<T>(self, path: ActionPath) -> Result<T>
     where
@@ -157,7 +252,7 @@ impl VISClient {
             .into_future();
 
         let (get_response, _stream) = get_stream.await;
-        get_response.unwrap().map_err(Into::into)
+        get_response.unwrap()
     }
 
     /// Subscribe to the given path's vehicle signals.
@@ -172,6 +267,7 @@ impl VISClient {
             path,
             filters,
             request_id,
+            initial_value: None,
         };
 
         let subscribe_msg = serde_json::to_string(&subscribe)?;
@@ -197,11 +293,35 @@ impl VISClient {
     }
 
     /// Subscribe to the given path's vehicle signals.
+    /// Missed notifications are not reported, see `subscribe_with_gaps`.
     pub async fn subscribe<T>(
         self,
         path: ActionPath,
         filters: Option<Filters>,
     ) -> Result<impl TryStream<Ok = (SubscriptionID, T), Error = VISClientError>>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(self
+            .subscribe_with_gaps(path, filters)
+            .await?
+            .try_filter_map(|event| {
+                future::ok(match event {
+                    SubscriptionEvent::Value(subscription_id, value) => {
+                        Some((subscription_id, value))
+                    }
+                    SubscriptionEvent::Gap { .. } => None,
+                })
+            }))
+    }
+
+    /// Subscribe to the given path's vehicle signals.
+    /// Missed notifications are reported as `SubscriptionEvent::Gap` before the next value.
+    pub async fn subscribe_with_gaps<T>(
+        self,
+        path: ActionPath,
+        filters: Option<Filters>,
+    ) -> Result<impl TryStream<Ok = SubscriptionEvent<T>, Error = VISClientError>>
     where
         T: DeserializeOwned,
     {
@@ -212,6 +332,7 @@ impl VISClient {
             path,
             filters,
             request_id,
+            initial_value: None,
         };
 
         let subscribe_msg = serde_json::to_string(&subscribe)?;
@@ -222,6 +343,8 @@ impl VISClient {
             .await?;
 
         let subscription_id: Arc<Mutex<Option<SubscriptionID>>> = Default::default();
+        // Sequence number of the last notification, to detect gaps
+        let last_sequence: Arc<Mutex<Option<u64>>> = Default::default();
 
         Ok(stream
             .compat()
@@ -242,20 +365,42 @@ impl VISClient {
                             }
                             // Store subscription_id to make sure the stream only returns values based on this subscription
                             *subscription_id.lock().unwrap() = Some(resp_subscription_id);
+                            *last_sequence.lock().unwrap() = Some(0);
                             future::ok(None)
                         }
                         Ok(ActionSuccessResponse::Subscription {
                             subscription_id: resp_subscription_id,
                             value,
+                            sequence,
                             ..
                         }) => {
                             if *subscription_id.lock().unwrap() != Some(resp_subscription_id) {
                                 return future::ok(None);
                             }
 
+                            let mut events = Vec::new();
+                            // Servers without sequence numbers can not report gaps
+                            if let Some(sequence) = sequence {
+                                let mut last_sequence = last_sequence.lock().unwrap();
+                                let missed = last_sequence
+                                    .and_then(|last| sequence.checked_sub(last + 1))
+                                    .unwrap_or_default();
+                                if missed > 0 {
+                                    events.push(SubscriptionEvent::Gap {
+                                        subscription_id: resp_subscription_id,
+                                        missed,
+                                    });
+                                }
+                                *last_sequence = Some(sequence);
+                            }
+
                             match serde_json::from_value::<T>(value) {
                                 Ok(stream_value) => {
-                                    future::ok(Some((resp_subscription_id, stream_value)))
+                                    events.push(SubscriptionEvent::Value(
+                                        resp_subscription_id,
+                                        stream_value,
+                                    ));
+                                    future::ok(Some(events))
                                 }
                                 // propagate deserialize error to stream
                                 Err(serde_error) => future::err(serde_error.into()),
@@ -269,6 +414,9 @@ impl VISClient {
                     future::ok(None)
                 }
             })
+            // A gap is followed by the value that revealed it
+            .map_ok(|events| stream::iter(events.into_iter().map(Ok::<_, VISClientError>)))
+            .try_flatten()
             .map_err(Into::into))
     }
 
@@ -55,3 +55,19 @@ async fn receive_subscription_async() -> Result<(), VISClientError> {
         panic!("Unexpected Action response {:?}", response);
     }
 }
+
+#[runtime::test(Native)]
+async fn receive_subscription_with_gaps_async() -> Result<(), VISClientError> {
+    let client = VISClient::connect("ws://127.0.0.1:14430").await?;
+    let mut sub_stream = client
+        .subscribe_with_gaps::<u32>("Private.Example.Interval".into(), None)
+        .await
+        .expect("Failed to subscribe");
+    let response = sub_stream.try_next().await.expect("No next value");
+    if let Some(SubscriptionEvent::Value(_subscription_id, interval)) = response {
+        assert!(interval > 0);
+        Ok(())
+    } else {
+        panic!("Unexpected Action response {:?}", response);
+    }
+}
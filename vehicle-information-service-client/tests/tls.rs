@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "tls")]
+
+use openssl::nid::Nid;
+use openssl::ssl::{SslAcceptor, SslMethod, SslVerifyMode};
+use runtime::native::Native;
+use serde_json::{json, Value};
+use std::net::TcpListener;
+use std::thread;
+use vehicle_information_service_client::*;
+use websocket::sync::server::upgrade::IntoWs;
+use websocket::OwnedMessage;
+
+#[path = "../../vehicle-information-service/tests/common/certificate.rs"]
+mod certificate;
+
+use certificate::{certificate, KeyPair};
+
+fn identity((key, certificate): &KeyPair) -> Identity {
+    Identity::from_pkcs8(
+        &certificate.to_pem().unwrap(),
+        &key.private_key_to_pem_pkcs8().unwrap(),
+    )
+    .unwrap()
+}
+
+///
+/// Serve a single `get` request over `wss://` with a server certificate issued by the `ca`.
+/// The value of the response is the common name of the client certificate, `null` without one.
+///
+fn serve_get(ca: &KeyPair) -> String {
+    let (key, server) = certificate("server", Some(ca));
+    let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    acceptor.set_private_key(&key).unwrap();
+    acceptor.set_certificate(&server).unwrap();
+    acceptor.cert_store_mut().add_cert(ca.1.clone()).unwrap();
+    acceptor.set_verify(SslVerifyMode::PEER);
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("wss://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        // Rejected connections are checked on the client side
+        let stream = match acceptor.accept(stream) {
+            Ok(stream) => stream,
+            Err(_) => return,
+        };
+        let common_name = stream.ssl().peer_certificate().map(|certificate| {
+            let entry = certificate
+                .subject_name()
+                .entries_by_nid(Nid::COMMONNAME)
+                .next()
+                .unwrap();
+            String::from_utf8(entry.data().as_slice().to_vec()).unwrap()
+        });
+
+        let upgrade = match stream.into_ws() {
+            Ok(upgrade) => upgrade,
+            Err(_) => panic!("Expected a websocket handshake"),
+        };
+        let mut client = upgrade.accept().map_err(|(_, e)| e).unwrap();
+        if let OwnedMessage::Text(get) = client.recv_message().unwrap() {
+            let get: Value = serde_json::from_str(&get).unwrap();
+            let response = json!({
+                "action": "get",
+                "requestId": get["requestId"],
+                "value": common_name,
+                "timestamp": 0,
+            });
+            client
+                .send_message(&OwnedMessage::Text(response.to_string()))
+                .unwrap();
+        }
+    });
+    address
+}
+
+#[runtime::test(Native)]
+async fn connect_secure_presents_identity() -> Result<(), VISClientError> {
+    let ca = certificate("Test CA", None);
+    let address = serve_get(&ca);
+    let client = certificate("vehicle-client", Some(&ca));
+
+    let client =
+        VISClient::connect_secure(&address, &ca.1.to_pem().unwrap(), Some(identity(&client)))
+            .await?;
+    let common_name: Option<String> = client.get("Vehicle.Speed".into()).await?;
+    assert_eq!(Some("vehicle-client".to_string()), common_name);
+    Ok(())
+}
+
+#[runtime::test(Native)]
+async fn connect_secure_without_identity() -> Result<(), VISClientError> {
+    let ca = certificate("Test CA", None);
+    let address = serve_get(&ca);
+
+    let client = VISClient::connect_secure(&address, &ca.1.to_pem().unwrap(), None).await?;
+    let common_name: Option<String> = client.get("Vehicle.Speed".into()).await?;
+    assert_eq!(None, common_name);
+    Ok(())
+}
+
+#[runtime::test(Native)]
+async fn connect_secure_err_when_server_not_trusted() {
+    let ca = certificate("Test CA", None);
+    let address = serve_get(&ca);
+    let other_ca = certificate("Other CA", None);
+
+    let result = VISClient::connect_secure(&address, &other_ca.1.to_pem().unwrap(), None).await;
+    assert!(result.is_err());
+}
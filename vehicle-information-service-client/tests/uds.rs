@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(unix)]
+
+use actix_web::rt::System;
+use actix_web::{App, HttpServer};
+use runtime::native::Native;
+use std::fs::remove_file;
+use std::path::Path;
+use std::thread;
+use vehicle_information_service::uds::{self, UdsConfig};
+use vehicle_information_service::{AppState, Router};
+use vehicle_information_service_client::*;
+
+/// Serve the websocket route on the socket at `path` until the test process exits.
+fn serve(path: &Path) {
+    let listener = UdsConfig {
+        path: path.to_path_buf(),
+        mode: Some(0o600),
+    }
+    .listener()
+    .unwrap();
+    thread::spawn(move || {
+        System::new("uds").block_on(async move {
+            HttpServer::new(move || {
+                let state = AppState::default();
+                state.set_signal("Vehicle.Speed".into(), 42);
+                App::new().data(state).configure(Router::configure_routes)
+            })
+            .workers(1)
+            .on_connect(uds::on_connect)
+            .listen_uds(listener)
+            .unwrap()
+            .run()
+            .await
+        })
+    });
+}
+
+#[runtime::test(Native)]
+async fn connect_uds_get() -> Result<(), VISClientError> {
+    let path = std::env::temp_dir().join(format!("vis-client-{}.sock", std::process::id()));
+    serve(&path);
+
+    let client = VISClient::connect_uds(&path).await?;
+    let speed: u32 = client.get("Vehicle.Speed".into()).await?;
+    assert_eq!(42, speed);
+    remove_file(&path)?;
+    Ok(())
+}
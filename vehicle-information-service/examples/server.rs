@@ -100,6 +100,7 @@ async fn main() -> std::io::Result<()> {
             .data(app_state)
             .wrap(middleware::Logger::default())
             .configure(Router::configure_routes)
+            .configure(Router::configure_http_routes)
             .default_service(web::route().to(|| HttpResponse::NotFound()))
     })
     .bind(socket_addr)?
@@ -139,6 +140,7 @@ impl Actor for IntervalSignalSource {
                 let update = UpdateSignal {
                     path: PATH_PRIVATE_EXAMPLE_INTERVAL.into(),
                     value: json!(v),
+                    timestamp: None,
                 };
                 act.signal_manager_addr.do_send(update);
             }))
@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! Path based access control for `get`, `set` and `subscribe` requests.
+//! Rules grant sessions authorized for a scope read and/or write access to path patterns.
+//!
+
+use actix::prelude::*;
+
+use crate::action::Authorization;
+use crate::api_error::{
+    KnownError, FORBIDDEN_USER_FORBIDDEN, FORBIDDEN_USER_UNKNOWN, UNAUTHORIZED_READ_ONLY,
+};
+use crate::api_type::ActionPath;
+use crate::signal_manager::SignalManager;
+
+#[cfg(test)]
+mod tests {
+    use crate::access_control::*;
+    use std::time::{Duration, Instant};
+
+    fn authorization(scope: &str) -> Authorization {
+        Authorization {
+            scope: scope.to_string(),
+            expires_at: Instant::now() + Duration::from_secs(60),
+        }
+    }
+
+    fn access_control() -> AccessControl {
+        AccessControl::default()
+            .allow("third_party", "Vehicle.Speed".into(), Access::Read)
+            .allow("oem", "Vehicle.*".into(), Access::ReadWrite)
+            .allow_public("Vehicle.VehicleIdentification".into(), Access::Read)
+    }
+
+    #[test]
+    fn check_ok_when_no_rules() {
+        assert_eq!(
+            Ok(()),
+            AccessControl::default().check(None, &"Vehicle.Cabin.Light".into(), Access::Write)
+        );
+    }
+
+    #[test]
+    fn check_read_ok_when_scope_has_read_access() {
+        let third_party = authorization("third_party");
+        assert_eq!(
+            Ok(()),
+            access_control().check(Some(&third_party), &"Vehicle.Speed".into(), Access::Read)
+        );
+    }
+
+    #[test]
+    fn check_write_read_only_when_scope_has_read_access() {
+        let third_party = authorization("third_party");
+        assert_eq!(
+            Err(UNAUTHORIZED_READ_ONLY),
+            access_control().check(Some(&third_party), &"Vehicle.Speed".into(), Access::Write)
+        );
+    }
+
+    #[test]
+    fn check_forbidden_when_scope_has_no_rule() {
+        let third_party = authorization("third_party");
+        assert_eq!(
+            Err(FORBIDDEN_USER_FORBIDDEN),
+            access_control().check(
+                Some(&third_party),
+                &"Vehicle.Cabin.Light".into(),
+                Access::Write
+            )
+        );
+    }
+
+    #[test]
+    fn check_wildcard_rule() {
+        let oem = authorization("oem");
+        assert_eq!(
+            Ok(()),
+            access_control().check(Some(&oem), &"Vehicle.Cabin.Light".into(), Access::Write)
+        );
+    }
+
+    #[test]
+    fn check_public_rule_and_unknown_user() {
+        assert_eq!(
+            Ok(()),
+            access_control().check(
+                None,
+                &"Vehicle.VehicleIdentification.VIN".into(),
+                Access::Read
+            )
+        );
+        assert_eq!(
+            Err(FORBIDDEN_USER_UNKNOWN),
+            access_control().check(None, &"Vehicle.Speed".into(), Access::Read)
+        );
+    }
+}
+
+///
+/// Kind of access to a signal path.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// `get` and `subscribe`
+    Read,
+    /// `set`
+    Write,
+    /// `get`, `subscribe` and `set`
+    ReadWrite,
+}
+
+impl Access {
+    /// Whether this access includes the `requested` access.
+    pub fn allows(self, requested: Access) -> bool {
+        self == Access::ReadWrite || self == requested
+    }
+}
+
+///
+/// Grants access to all paths matching `path`, see `ActionPath::matches`.
+///
+#[derive(Clone, Debug)]
+pub struct AccessRule {
+    /// Scope the rule applies to, `None` applies to all clients, including unauthorized clients.
+    pub scope: Option<String>,
+    pub path: ActionPath,
+    pub access: Access,
+}
+
+///
+/// Access control list that is checked for each `get`, `set` and `subscribe` request.
+/// Without any rules, all clients may access all paths.
+///
+/// # Examples
+/// ```
+/// use vehicle_information_service::{Access, AccessControl};
+///
+/// let access_control = AccessControl::default()
+///     .allow("third_party", "Vehicle.Speed".into(), Access::Read)
+///     .allow("oem", "Vehicle.Cabin.*".into(), Access::ReadWrite);
+/// ```
+///
+#[derive(Clone, Debug, Default)]
+pub struct AccessControl {
+    rules: Vec<AccessRule>,
+}
+
+impl AccessControl {
+    /// Grant clients authorized for `scope` access to `path`.
+    pub fn allow(mut self, scope: &str, path: ActionPath, access: Access) -> Self {
+        self.rules.push(AccessRule {
+            scope: Some(scope.to_string()),
+            path,
+            access,
+        });
+        self
+    }
+
+    /// Grant all clients access to `path`, whether they are authorized or not.
+    pub fn allow_public(mut self, path: ActionPath, access: Access) -> Self {
+        self.rules.push(AccessRule {
+            scope: None,
+            path,
+            access,
+        });
+        self
+    }
+
+    pub fn rules(&self) -> &[AccessRule] {
+        &self.rules
+    }
+
+    ///
+    /// Check whether a client with the given authorization has the `requested` access to `path`.
+    /// Returns the error that is to be sent to the client if not.
+    ///
+    pub fn check(
+        &self,
+        authorization: Option<&Authorization>,
+        path: &ActionPath,
+        requested: Access,
+    ) -> Result<(), KnownError> {
+        if self.rules.is_empty() {
+            return Ok(());
+        }
+
+        let scope = authorization.map(|authorization| authorization.scope.as_str());
+        let matching_rules = self.rules.iter().filter(|rule| {
+            (rule.scope.is_none() || rule.scope.as_deref() == scope) && rule.path.matches(path)
+        });
+
+        let mut readable = false;
+        for rule in matching_rules {
+            if rule.access.allows(requested) {
+                return Ok(());
+            }
+            readable = readable || rule.access.allows(Access::Read);
+        }
+
+        if requested == Access::Write && readable {
+            Err(UNAUTHORIZED_READ_ONLY)
+        } else if authorization.is_none() {
+            Err(FORBIDDEN_USER_UNKNOWN)
+        } else {
+            Err(FORBIDDEN_USER_FORBIDDEN)
+        }
+    }
+}
+
+///
+/// Replace the access control list of the `SignalManager`.
+///
+pub struct SetAccessControl {
+    pub access_control: AccessControl,
+}
+
+impl Message for SetAccessControl {
+    type Result = ();
+}
+
+impl Handler<SetAccessControl> for SignalManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetAccessControl, _ctx: &mut Self::Context) {
+        self.access_control = msg.access_control;
+    }
+}
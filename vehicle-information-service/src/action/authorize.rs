@@ -1,22 +1,399 @@
 // SPDX-License-Identifier: MIT
 
-use actix::prelude::*;
+//!
+//! Validate client tokens on AUTHORIZE requests using an application provided `Authorizer`.
+//!
+
+use actix_web::HttpRequest;
+use http::status::StatusCode;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+use crate::api_error::{
+    new_authorize_error, ActionErrorResponse, KnownError, BAD_REQUEST,
+    UNAUTHORIZED_TOO_MANY_ATTEMPTS, UNAUTHORIZED_USER_TOKEN_MISSING,
+};
+use crate::api_type::ReqID;
+
+#[cfg(test)]
+mod tests {
+    use crate::action::authorize::*;
+    use crate::api_error::{ActionError, UNAUTHORIZED_USER_TOKEN_INVALID};
+    use serde_json::json;
+
+    struct TestAuthorizer;
+
+    impl Authorizer for TestAuthorizer {
+        fn authorize(&self, tokens: &Tokens) -> Result<Grant, KnownError> {
+            let common_name = tokens
+                .client_identity
+                .as_ref()
+                .map(|identity| identity.common_name.as_str());
+            let root = tokens
+                .peer_credentials
+                .filter(|credentials| credentials.uid == 0)
+                .map(|_| "valid");
+            match tokens.authorization.as_deref().or(common_name).or(root) {
+                Some("valid") => Ok(Grant {
+                    scope: "test".to_string(),
+                    ttl: Duration::from_secs(60),
+                }),
+                _ => Err(UNAUTHORIZED_USER_TOKEN_INVALID),
+            }
+        }
+    }
+
+    fn authorize_error(response: ActionErrorResponse) -> ActionError {
+        if let ActionErrorResponse::Authorize { error, .. } = response {
+            error
+        } else {
+            panic!("Unexpected error response {}", response);
+        }
+    }
+
+    #[test]
+    fn tokens_from_value() {
+        let tokens =
+            Tokens::from_value(&json!({ "authorization": "a", "www-vehicle-device": "d" }))
+                .unwrap();
+        assert_eq!(Some("a".to_string()), tokens.authorization);
+        assert_eq!(Some("d".to_string()), tokens.device);
+    }
+
+    #[test]
+    fn tokens_from_value_err_when_not_object() {
+        assert!(Tokens::from_value(&json!("token")).is_err());
+        assert!(Tokens::from_value(&json!({ "authorization": 1 })).is_err());
+    }
+
+    #[test]
+    fn authorize_grants_scope() {
+        let authorization = authorize(
+            Some(&TestAuthorizer),
+            &mut 0,
+            &json!({ "authorization": "valid" }),
+            ReqID::default(),
+            &ConnectionInfo::default(),
+        )
+        .unwrap();
+        assert_eq!("test", authorization.scope);
+        assert!(!authorization.is_expired());
+    }
+
+    #[test]
+    fn authorize_client_identity_without_tokens() {
+        let identity = ClientIdentity {
+            common_name: "valid".to_string(),
+        };
+        let authorization = authorize(
+            Some(&TestAuthorizer),
+            &mut 0,
+            &json!({}),
+            ReqID::default(),
+            &ConnectionInfo {
+                client_identity: Some(identity),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!("test", authorization.scope);
+    }
+
+    #[test]
+    fn authorize_peer_credentials_without_tokens() {
+        let credentials = PeerCredentials { uid: 0, gid: 0 };
+        let authorization = authorize(
+            Some(&TestAuthorizer),
+            &mut 0,
+            &json!({}),
+            ReqID::default(),
+            &ConnectionInfo {
+                peer_credentials: Some(credentials),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!("test", authorization.scope);
+    }
+
+    #[test]
+    fn authorize_connection_without_tokens() {
+        let connection = |uid| ConnectionInfo {
+            peer_credentials: Some(PeerCredentials { uid, gid: 0 }),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some("test".to_string()),
+            authorize_connection(Some(&TestAuthorizer), &connection(0)).map(|a| a.scope)
+        );
+        assert_eq!(
+            None,
+            authorize_connection(Some(&TestAuthorizer), &connection(1000))
+        );
+        assert_eq!(None, authorize_connection(None, &connection(0)));
+        assert_eq!(
+            None,
+            authorize_connection(Some(&TestAuthorizer), &ConnectionInfo::default())
+        );
+    }
+
+    #[test]
+    fn authorize_err_when_token_missing() {
+        let error = authorize(
+            Some(&TestAuthorizer),
+            &mut 0,
+            &json!({}),
+            ReqID::default(),
+            &ConnectionInfo::default(),
+        )
+        .unwrap_err();
+        let expected: ActionError = UNAUTHORIZED_USER_TOKEN_MISSING.into();
+        assert_eq!(expected, authorize_error(error));
+    }
+
+    #[test]
+    fn authorize_counts_only_rejected_credentials() {
+        let mut failed_attempts = 0;
+        let authorize_with =
+            |authorizer: Option<&dyn Authorizer>, tokens: Value, failed_attempts: &mut usize| {
+                authorize(
+                    authorizer,
+                    failed_attempts,
+                    &tokens,
+                    ReqID::default(),
+                    &ConnectionInfo::default(),
+                )
+            };
+
+        assert!(
+            authorize_with(None, json!({ "authorization": "x" }), &mut failed_attempts).is_err()
+        );
+        assert!(authorize_with(Some(&TestAuthorizer), json!({}), &mut failed_attempts).is_err());
+        assert!(authorize_with(
+            Some(&TestAuthorizer),
+            json!({ "authorization": 1 }),
+            &mut failed_attempts
+        )
+        .is_err());
+        assert_eq!(0, failed_attempts);
+
+        assert!(authorize_with(
+            Some(&TestAuthorizer),
+            json!({ "authorization": "x" }),
+            &mut failed_attempts
+        )
+        .is_err());
+        assert_eq!(1, failed_attempts);
+    }
+
+    #[test]
+    fn authorize_err_when_too_many_attempts() {
+        let mut failed_attempts = MAX_FAILED_AUTHORIZE_ATTEMPTS;
+        let error = authorize(
+            Some(&TestAuthorizer),
+            &mut failed_attempts,
+            &json!({ "authorization": "valid" }),
+            ReqID::default(),
+            &ConnectionInfo::default(),
+        )
+        .unwrap_err();
+        let expected: ActionError = UNAUTHORIZED_TOO_MANY_ATTEMPTS.into();
+        assert_eq!(expected, authorize_error(error));
+    }
+}
+
+/// Number of failed authorize attempts after which a client session is no longer
+/// allowed to authorize.
+pub const MAX_FAILED_AUTHORIZE_ATTEMPTS: usize = 5;
+
+///
+/// Tokens sent by the client in an AUTHORIZE request.
+/// [Authorize Doc](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#dfn-authorizerequest)
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tokens {
+    /// User token, passed as `authorization`.
+    pub authorization: Option<String>,
+    /// Device token, passed as `www-vehicle-device`.
+    pub device: Option<String>,
+    /// Identity of the verified TLS client certificate of the connection, set by the server.
+    pub client_identity: Option<ClientIdentity>,
+    /// Credentials of the peer process of a Unix domain socket connection, set by the server.
+    pub peer_credentials: Option<PeerCredentials>,
+}
+
+///
+/// Identity of a client authenticated by a TLS client certificate, see the `tls` module.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// Common name of the certificate subject
+    pub common_name: String,
+}
+
+///
+/// User and group of a local client connected by a Unix domain socket, see the `uds` module.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeerCredentials {
+    /// Effective user id of the peer process when it connected
+    pub uid: u32,
+    /// Effective group id of the peer process when it connected
+    pub gid: u32,
+}
 
 ///
-/// AUTHORIZE request
-/// https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#dfn-authorizerequest
+/// Authentication of the connection of a client session or HTTP request, set by the server.
 ///
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "action")]
-struct AuthorizeMessage {
-    tokens: Value,
-    #[serde(rename = "requestId")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ConnectionInfo {
+    /// Identity of the verified TLS client certificate, see the `tls` module.
+    pub client_identity: Option<ClientIdentity>,
+    /// User and group of the peer process of a Unix domain socket connection, see the `uds` module.
+    pub peer_credentials: Option<PeerCredentials>,
+}
+
+impl ConnectionInfo {
+    /// Connection info stored in the request extensions by `tls::on_connect` or `uds::on_connect`.
+    pub fn from_request(r: &HttpRequest) -> Self {
+        let extensions = r.extensions();
+        Self {
+            client_identity: extensions.get::<ClientIdentity>().cloned(),
+            peer_credentials: extensions.get::<PeerCredentials>().copied(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.client_identity.is_none() && self.peer_credentials.is_none()
+    }
+}
+
+impl Tokens {
+    /// Extract the tokens from the `tokens` value of an AUTHORIZE request.
+    pub fn from_value(tokens: &Value) -> Result<Self, KnownError> {
+        let tokens = tokens.as_object().ok_or(BAD_REQUEST)?;
+        let token = |key| match tokens.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(token)) => Ok(Some(token.clone())),
+            Some(_) => Err(BAD_REQUEST),
+        };
+
+        Ok(Self {
+            authorization: token("authorization")?,
+            device: token("www-vehicle-device")?,
+            client_identity: None,
+            peer_credentials: None,
+        })
+    }
+}
+
+///
+/// Access granted by an `Authorizer`.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grant {
+    /// Scope the client is granted, e.g. `third_party`.
+    pub scope: String,
+    /// Time until the grant expires and the client has to authorize again.
+    pub ttl: Duration,
+}
+
+///
+/// Validates client tokens. Register an `Authorizer` on the `AppState` to enable the AUTHORIZE action.
+///
+/// Return one of the `UNAUTHORIZED_USER_TOKEN_*` or `UNAUTHORIZED_DEVICE_TOKEN_*` errors
+/// if the tokens are rejected.
+///
+pub trait Authorizer: Send + Sync {
+    fn authorize(&self, tokens: &Tokens) -> Result<Grant, KnownError>;
+}
+
+///
+/// Authorization state of a client session.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Authorization {
+    /// Granted scope
+    pub scope: String,
+    /// Point in time after which the authorization is no longer valid
+    pub expires_at: Instant,
+}
+
+impl Authorization {
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+}
+
+impl From<Grant> for Authorization {
+    fn from(grant: Grant) -> Self {
+        Self {
+            scope: grant.scope,
+            expires_at: Instant::now() + grant.ttl,
+        }
+    }
+}
+
+///
+/// Validate the tokens of an AUTHORIZE request.
+/// `failed_attempts` is the number of previously rejected attempts of the client, it is increased
+/// when the `Authorizer` rejects the credentials. Malformed requests are not counted.
+/// Clients with a verified TLS client certificate or connected by a Unix domain socket may authorize without tokens.
+///
+pub(crate) fn authorize(
+    authorizer: Option<&dyn Authorizer>,
+    failed_attempts: &mut usize,
+    tokens: &Value,
     request_id: ReqID,
+    connection: &ConnectionInfo,
+) -> Result<Authorization, ActionErrorResponse> {
+    let authorizer = authorizer
+        .ok_or_else(|| new_authorize_error(request_id, StatusCode::NOT_IMPLEMENTED.into()))?;
+
+    if *failed_attempts >= MAX_FAILED_AUTHORIZE_ATTEMPTS {
+        return Err(new_authorize_error(
+            request_id,
+            UNAUTHORIZED_TOO_MANY_ATTEMPTS.into(),
+        ));
+    }
+
+    let mut tokens =
+        Tokens::from_value(tokens).map_err(|e| new_authorize_error(request_id, e.into()))?;
+    tokens.client_identity = connection.client_identity.clone();
+    tokens.peer_credentials = connection.peer_credentials;
+
+    if tokens.authorization.is_none() && tokens.device.is_none() && connection.is_empty() {
+        return Err(new_authorize_error(
+            request_id,
+            UNAUTHORIZED_USER_TOKEN_MISSING.into(),
+        ));
+    }
+
+    authorizer.authorize(&tokens).map(Into::into).map_err(|e| {
+        *failed_attempts += 1;
+        new_authorize_error(request_id, e.into())
+    })
 }
 
-impl Handler for Authorize {
-    type Result = Result<Ok, Error>;
-    fn handle(&mut self, authorize: Authorize, ctx: &mut ws::WebsocketContext<Self>) -> Self::Result {
-        unimplemented!();
+///
+/// Authorize a client by the TLS client certificate or Unix domain socket peer credentials of its connection,
+/// before it sent any tokens. Websocket sessions are authorized this way when they start,
+/// HTTP requests without tokens on every request.
+/// A rejected connection is not counted as a failed attempt, the client may still authorize with tokens.
+///
+pub(crate) fn authorize_connection(
+    authorizer: Option<&dyn Authorizer>,
+    connection: &ConnectionInfo,
+) -> Option<Authorization> {
+    if connection.is_empty() {
+        return None;
     }
-}
\ No newline at end of file
+    authorize(
+        Some(authorizer?),
+        &mut 0,
+        &Value::Object(Default::default()),
+        ReqID::default(),
+        connection,
+    )
+    .ok()
+}
@@ -2,16 +2,336 @@
 
 //!
 //! Retrieve the current state of a signal and respond to the requesting client.
+//! Branch paths e.g. `Signal.Drivetrain` and wildcard paths e.g. `Signal.*.RPM` retrieve
+//! all matching signals as an object keyed by signal path.
+//! Signals with a registered `GetRequest` recipient are requested on demand if they are
+//! not cached or the cached value is stale.
 //!
 
 use actix::prelude::*;
+use serde_json::{Map, Value};
+use std::time::Duration;
 
-use crate::action::ClientMessage;
-use crate::api_error::{ActionErrorResponse, NOT_FOUND_INVALID_PATH};
+use crate::access_control::Access;
+use crate::action::{Authorization, ClientMessage};
+use crate::api_error::{
+    ActionErrorResponse, KnownError, GATEWAY_TIMEOUT, NOT_FOUND_INVALID_PATH,
+    NOT_FOUND_UNAVAILABLE_DATA, SERVICE_UNAVAILABLE,
+};
 use crate::api_type::{ActionPath, ActionSuccessResponse, ReqID};
-use crate::signal_manager::SignalManager;
+use crate::codec::{DataPoint, Response};
+use crate::signal_manager::{CachedSignal, SignalManager};
 use crate::unix_timestamp_ms;
 
+#[cfg(test)]
+mod tests {
+    use crate::access_control::{Access, AccessControl};
+    use crate::action::get::*;
+    use crate::api_error::{
+        FORBIDDEN_USER_UNKNOWN, NOT_FOUND_INVALID_PATH, NOT_FOUND_UNAVAILABLE_DATA,
+    };
+    use crate::codec::Codec;
+    use crate::config::Config;
+    use crate::outbound::{Outbox, SlowConsumerPolicy};
+    use crate::router::ClientAddr;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+    use uuid::Uuid;
+
+    fn signal_manager() -> SignalManager {
+        let mut signal_manager = SignalManager::default();
+        for (path, value, timestamp) in &[
+            (
+                "Signal.Drivetrain.InternalCombustionEngine.RPM",
+                json!(2000),
+                1000,
+            ),
+            ("Signal.Drivetrain.Transmission.Gear", json!(3), 2000),
+            ("Signal.Chassis.Axle.RPM", json!(800), 3000),
+        ] {
+            signal_manager.signal_cache.insert(
+                (*path).into(),
+                CachedSignal {
+                    value: value.clone(),
+                    timestamp: *timestamp,
+                    received: Instant::now(),
+                    available: true,
+                },
+            );
+        }
+        signal_manager
+    }
+
+    fn get_with_timestamp(
+        signal_manager: &SignalManager,
+        path: &str,
+    ) -> Result<(Value, u128), KnownError> {
+        let path = path.into();
+        signal_manager
+            .get_value(&path, None)
+            .map(|data| response_value(&path, &data))
+    }
+
+    fn get(signal_manager: &SignalManager, path: &str) -> Result<Value, KnownError> {
+        get_with_timestamp(signal_manager, path).map(|(value, _timestamp)| value)
+    }
+
+    #[test]
+    fn get_value_exact_path() {
+        assert_eq!(
+            Ok(json!(3)),
+            get(&signal_manager(), "Signal.Drivetrain.Transmission.Gear")
+        );
+    }
+
+    #[test]
+    fn get_value_branch_path() {
+        assert_eq!(
+            Ok(json!({
+                "Signal.Drivetrain.InternalCombustionEngine.RPM": 2000,
+                "Signal.Drivetrain.Transmission.Gear": 3,
+            })),
+            get(&signal_manager(), "Signal.Drivetrain")
+        );
+    }
+
+    #[test]
+    fn get_value_wildcard_path() {
+        assert_eq!(
+            Ok(json!({ "Signal.Chassis.Axle.RPM": 800 })),
+            get(&signal_manager(), "Signal.*.Axle.RPM")
+        );
+    }
+
+    #[test]
+    fn get_value_not_found() {
+        assert_eq!(
+            Err(NOT_FOUND_INVALID_PATH),
+            get(&signal_manager(), "Signal.Cabin")
+        );
+    }
+
+    #[test]
+    fn get_value_omits_forbidden_signals() {
+        let mut signal_manager = signal_manager();
+        signal_manager.access_control =
+            AccessControl::default().allow_public("Signal.Chassis".into(), Access::Read);
+        assert_eq!(
+            Ok(json!({ "Signal.Chassis.Axle.RPM": 800 })),
+            get(&signal_manager, "Signal.*")
+        );
+        assert_eq!(
+            Err(FORBIDDEN_USER_UNKNOWN),
+            get(&signal_manager, "Signal.Drivetrain")
+        );
+    }
+
+    #[test]
+    fn get_value_forbidden_does_not_reveal_existence() {
+        let mut signal_manager = signal_manager();
+        signal_manager.access_control =
+            AccessControl::default().allow_public("Signal.Chassis".into(), Access::Read);
+        assert_eq!(
+            Err(FORBIDDEN_USER_UNKNOWN),
+            get(&signal_manager, "Signal.Drivetrain.Transmission.Gear")
+        );
+        assert_eq!(
+            Err(FORBIDDEN_USER_UNKNOWN),
+            get(&signal_manager, "Signal.Drivetrain.Transmission.Invalid")
+        );
+        assert_eq!(
+            Err(FORBIDDEN_USER_UNKNOWN),
+            get(&signal_manager, "Signal.Cabin")
+        );
+        assert_eq!(
+            Err(NOT_FOUND_INVALID_PATH),
+            get(&signal_manager, "Signal.Chassis.Invalid")
+        );
+    }
+
+    #[test]
+    fn get_value_unavailable() {
+        let mut signal_manager = signal_manager();
+        signal_manager.invalidate_signal(&"Signal.Chassis.Axle.RPM".into());
+        assert_eq!(
+            Err(NOT_FOUND_UNAVAILABLE_DATA),
+            get(&signal_manager, "Signal.Chassis.Axle.RPM")
+        );
+        assert_eq!(
+            Err(NOT_FOUND_UNAVAILABLE_DATA),
+            get(&signal_manager, "Signal.Chassis")
+        );
+        assert_eq!(
+            Ok(json!({
+                "Signal.Drivetrain.InternalCombustionEngine.RPM": 2000,
+                "Signal.Drivetrain.Transmission.Gear": 3,
+            })),
+            get(&signal_manager, "Signal")
+        );
+    }
+
+    #[test]
+    fn get_value_timestamp_of_latest_measurement() {
+        let signal_manager = signal_manager();
+        assert_eq!(
+            Ok(2000),
+            get_with_timestamp(&signal_manager, "Signal.Drivetrain.Transmission.Gear")
+                .map(|(_value, timestamp)| timestamp)
+        );
+        assert_eq!(
+            Ok(3000),
+            get_with_timestamp(&signal_manager, "Signal").map(|(_value, timestamp)| timestamp)
+        );
+    }
+
+    #[test]
+    fn get_provider_is_stale() {
+        let cached = CachedSignal {
+            value: json!("WVWZZZ"),
+            timestamp: 0,
+            received: Instant::now() - Duration::from_secs(10),
+            available: true,
+        };
+        assert!(!get_provider(None).is_stale(&cached));
+        assert!(!get_provider(Some(Duration::from_secs(60))).is_stale(&cached));
+        assert!(get_provider(Some(Duration::from_secs(1))).is_stale(&cached));
+    }
+
+    /// Provider that responds with `result`, or never responds if `result` is `None`.
+    struct Provider {
+        result: Option<Result<Value, KnownError>>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl Actor for Provider {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<GetRequest> for Provider {
+        type Result = ResponseFuture<Result<Value, KnownError>>;
+
+        fn handle(&mut self, _msg: GetRequest, _ctx: &mut Self::Context) -> Self::Result {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            match self.result.clone() {
+                Some(result) => Box::pin(futures::future::ready(result)),
+                None => Box::pin(futures::future::pending()),
+            }
+        }
+    }
+
+    fn get_provider(max_age: Option<Duration>) -> GetProvider {
+        let _system = System::new("get-provider");
+        let provider = Provider {
+            result: Some(Err(NOT_FOUND_INVALID_PATH)),
+            requests: Default::default(),
+        };
+        GetProvider {
+            recipient: provider.start().recipient(),
+            max_age,
+        }
+    }
+
+    ///
+    /// Get the VIN from a `SignalManager` with a provider that responds with `result`, the VIN is cached
+    /// with the given age. Returns the response to the client and the number of provider requests.
+    ///
+    fn get_from_provider(
+        cache_age: Option<Duration>,
+        max_age: Option<Duration>,
+        result: Option<Result<Value, KnownError>>,
+    ) -> (Value, usize) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let provider_requests = requests.clone();
+        let response = System::new("get-provider").block_on(async move {
+            let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+            let client_addr = ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::Legacy);
+            let path: ActionPath = "Vehicle.VehicleIdentification.VIN".into();
+            let mut signal_manager = SignalManager::new(Config {
+                get_timeout: Duration::from_millis(50),
+                ..Default::default()
+            });
+            if let Some(cache_age) = cache_age {
+                signal_manager.signal_cache.insert(
+                    path.clone(),
+                    CachedSignal {
+                        value: json!("WVWZZZ"),
+                        timestamp: 0,
+                        received: Instant::now() - cache_age,
+                        available: true,
+                    },
+                );
+            }
+            let provider = Provider {
+                result,
+                requests: provider_requests,
+            };
+            signal_manager.get_providers.insert(
+                path.clone(),
+                GetProvider {
+                    recipient: provider.start().recipient(),
+                    max_age,
+                },
+            );
+
+            signal_manager
+                .start()
+                .send(ClientMessage {
+                    client_connection_id: client_addr.client_connection_id(),
+                    client_addr,
+                    authorization: None,
+                    message: Get {
+                        path,
+                        request_id: ReqID::default(),
+                    },
+                })
+                .await
+                .unwrap();
+            let response = outbox.recv().await.unwrap();
+            serde_json::from_str::<Value>(&response.into_text()).unwrap()
+        });
+        (response, requests.load(Ordering::SeqCst))
+    }
+
+    #[test]
+    fn get_provider_not_asked_for_fresh_value() {
+        let (response, requests) = get_from_provider(
+            Some(Duration::from_secs(10)),
+            Some(Duration::from_secs(60)),
+            Some(Ok(json!("WDDZZZ"))),
+        );
+        assert_eq!(json!("WVWZZZ"), response["value"]);
+        assert_eq!(0, requests);
+    }
+
+    #[test]
+    fn get_provider_asked_for_stale_value() {
+        let (response, requests) = get_from_provider(
+            Some(Duration::from_secs(10)),
+            Some(Duration::from_secs(1)),
+            Some(Ok(json!("WDDZZZ"))),
+        );
+        assert_eq!(json!("WDDZZZ"), response["value"]);
+        assert_eq!(1, requests);
+    }
+
+    #[test]
+    fn get_provider_error() {
+        let (response, requests) =
+            get_from_provider(None, None, Some(Err(NOT_FOUND_UNAVAILABLE_DATA)));
+        assert_eq!(json!("unavailable_data"), response["error"]["reason"]);
+        assert_eq!(1, requests);
+    }
+
+    #[test]
+    fn get_provider_timeout() {
+        let (response, requests) = get_from_provider(None, None, None);
+        assert_eq!(json!("gateway_timeout"), response["error"]["reason"]);
+        assert_eq!(1, requests);
+    }
+}
+
 ///
 ///[Get](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#dfn-getrequest)
 ///
@@ -28,19 +348,212 @@ impl Message for ClientMessage<Get> {
 impl Handler<ClientMessage<Get>> for SignalManager {
     type Result = ();
 
-    fn handle(&mut self, msg: ClientMessage<Get>, _ctx: &mut Self::Context) {
-        if let Some(signal) = self.signal_cache.get(&msg.message.path) {
-            msg.client_addr.do_send(ActionSuccessResponse::Get {
-                request_id: msg.message.request_id,
-                value: signal.clone(),
-                timestamp: unix_timestamp_ms(),
-            });
+    fn handle(&mut self, msg: ClientMessage<Get>, ctx: &mut Self::Context) {
+        let path = msg.message.path.clone();
+        let provider = match self.get_providers.get(&path) {
+            Some(provider) => provider,
+            None => {
+                let result = self.get_value(&path, msg.authorization.as_ref());
+                return respond(&msg, result);
+            }
+        };
+
+        if let Some(cached) = self.signal_cache.get(&path) {
+            if cached.available && !provider.is_stale(cached) {
+                let result = self.get_value(&path, msg.authorization.as_ref());
+                return respond(&msg, result);
+            }
+        }
+
+        if let Err(error) = msg.check_access(self, &path, Access::Read) {
+            return respond(&msg, Err(error));
+        }
+
+        debug!("Requesting value for path {} from get provider", path);
+        let request = provider
+            .recipient
+            .send(GetRequest { path: path.clone() })
+            .timeout(self.config.get_timeout);
+        ctx.spawn(request.into_actor(self).map(move |result, act, ctx| {
+            let result = match result {
+                Ok(Ok(value)) => {
+                    let timestamp = unix_timestamp_ms();
+                    act.update_signal(path.clone(), value.clone(), timestamp, ctx);
+                    Ok(vec![DataPoint {
+                        path,
+                        value,
+                        timestamp,
+                    }])
+                }
+                Ok(Err(error)) => Err(error),
+                Err(MailboxError::Timeout) => {
+                    warn!("Get provider for path {} timed out", path);
+                    Err(GATEWAY_TIMEOUT)
+                }
+                Err(MailboxError::Closed) => {
+                    warn!("Get provider for path {} is closed", path);
+                    Err(SERVICE_UNAVAILABLE)
+                }
+            };
+            respond(&msg, result);
+        }));
+    }
+}
+
+fn respond(msg: &ClientMessage<Get>, result: Result<Vec<DataPoint>, KnownError>) {
+    match result {
+        Ok(data) => {
+            let (value, timestamp) = response_value(&msg.message.path, &data);
+            msg.client_addr.do_send(
+                Response::from(ActionSuccessResponse::Get {
+                    request_id: msg.message.request_id,
+                    value,
+                    timestamp,
+                })
+                .with_data(data),
+            )
+        }
+        Err(error) => msg.client_addr.do_send(ActionErrorResponse::Get {
+            request_id: msg.message.request_id,
+            timestamp: unix_timestamp_ms(),
+            error: error.into(),
+        }),
+    }
+}
+
+///
+/// Value of a `get` response and the timestamp of the latest measurement. The value of the signal for
+/// an exact path, for branch and wildcard paths an object containing the values keyed by signal path.
+///
+fn response_value(path: &ActionPath, data: &[DataPoint]) -> (Value, u128) {
+    match data {
+        [data_point] if data_point.path == *path => {
+            (data_point.value.clone(), data_point.timestamp)
+        }
+        data => {
+            let values = data
+                .iter()
+                .map(|data_point| (data_point.path.to_string(), data_point.value.clone()))
+                .collect::<Map<_, _>>();
+            let timestamp = data.iter().map(|data_point| data_point.timestamp).max();
+            (Value::Object(values), timestamp.unwrap_or_default())
+        }
+    }
+}
+
+impl SignalManager {
+    ///
+    /// Value of the signal with the given path and the timestamp it was measured.
+    /// For branch and wildcard paths all available matching signals the client has read access to.
+    ///
+    pub(crate) fn get_value(
+        &self,
+        path: &ActionPath,
+        authorization: Option<&Authorization>,
+    ) -> Result<Vec<DataPoint>, KnownError> {
+        // Checked regardless of the cache, the error must not reveal whether a forbidden path exists
+        let access = self.access_control.check(authorization, path, Access::Read);
+        if !path.is_wildcard() {
+            if let Some(signal) = self.signal_cache.get(path) {
+                access?;
+                if !signal.available {
+                    return Err(NOT_FOUND_UNAVAILABLE_DATA);
+                }
+                return Ok(vec![DataPoint {
+                    path: path.clone(),
+                    value: signal.value.clone(),
+                    timestamp: signal.timestamp,
+                }]);
+            }
+        }
+
+        let mut data = Vec::new();
+        let mut unavailable = false;
+        for (signal_path, signal) in self.signal_cache.iter() {
+            if !path.matches(signal_path) {
+                continue;
+            }
+
+            match self
+                .access_control
+                .check(authorization, signal_path, Access::Read)
+            {
+                Ok(()) if !signal.available => {
+                    unavailable = true;
+                }
+                Ok(()) => data.push(DataPoint {
+                    path: signal_path.clone(),
+                    value: signal.value.clone(),
+                    timestamp: signal.timestamp,
+                }),
+                Err(_) => {}
+            }
+        }
+
+        if data.is_empty() {
+            access?;
+            Err(if unavailable {
+                NOT_FOUND_UNAVAILABLE_DATA
+            } else {
+                NOT_FOUND_INVALID_PATH
+            })
         } else {
-            msg.client_addr.do_send(ActionErrorResponse::Get {
-                request_id: msg.message.request_id,
-                timestamp: unix_timestamp_ms(),
-                error: NOT_FOUND_INVALID_PATH.into(),
-            });
+            Ok(data)
         }
     }
 }
+
+///
+/// Request for the current value of a signal, sent to the recipient registered via `AddGetRecipient`.
+///
+#[derive(Clone, Debug)]
+pub struct GetRequest {
+    pub path: ActionPath,
+}
+
+impl Message for GetRequest {
+    type Result = Result<Value, KnownError>;
+}
+
+pub(crate) struct GetProvider {
+    pub recipient: Recipient<GetRequest>,
+    /// Maximum age of a cached value before the provider is asked again, `None` if cached values do not expire.
+    pub max_age: Option<Duration>,
+}
+
+impl GetProvider {
+    pub fn is_stale(&self, cached: &CachedSignal) -> bool {
+        self.max_age
+            .map(|max_age| cached.received.elapsed() > max_age)
+            .unwrap_or(false)
+    }
+}
+
+///
+/// Register a `GetRequest` recipient for a signal path.
+/// The recipient is only asked for `get` requests of exactly this path, `get` requests of
+/// branch and wildcard paths are answered from the signal cache.
+///
+pub struct AddGetRecipient {
+    pub path: ActionPath,
+    pub recipient: Recipient<GetRequest>,
+    pub max_age: Option<Duration>,
+}
+
+impl Message for AddGetRecipient {
+    type Result = ();
+}
+
+impl Handler<AddGetRecipient> for SignalManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: AddGetRecipient, _ctx: &mut Self::Context) {
+        self.get_providers.insert(
+            msg.path,
+            GetProvider {
+                recipient: msg.recipient,
+                max_age: msg.max_age,
+            },
+        );
+    }
+}
@@ -1,10 +1,54 @@
 // SPDX-License-Identifier: MIT
 
+//!
+//! Retrieve the metadata of a signal path and respond to the requesting client.
+//! Only signals the client has access to are included.
+//!
+
+use actix::prelude::*;
+
+use crate::access_control::Access;
+use crate::action::ClientMessage;
+use crate::api_error::{ActionErrorResponse, NOT_FOUND_INVALID_PATH};
+use crate::api_type::{ActionPath, ActionSuccessResponse, ReqID};
+use crate::signal_manager::SignalManager;
+use crate::unix_timestamp_ms;
+
 ///
-/// GET_VSS request
+/// [GetMetadata](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#dfn-metadatarequest)
 ///
 #[derive(Debug)]
-struct GetMetadata {
-    path: ActionPath,
-    request_id: ReqID,
-}
\ No newline at end of file
+pub struct GetMetadata {
+    pub path: ActionPath,
+    pub request_id: ReqID,
+}
+
+impl Message for ClientMessage<GetMetadata> {
+    type Result = ();
+}
+
+impl Handler<ClientMessage<GetMetadata>> for SignalManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClientMessage<GetMetadata>, _ctx: &mut Self::Context) {
+        // Signals the client can neither read nor write are omitted, forbidden and missing
+        // paths are both answered with `invalid_path`
+        let visible = |path: &ActionPath| {
+            msg.check_access(self, path, Access::Read).is_ok()
+                || msg.check_access(self, path, Access::Write).is_ok()
+        };
+        if let Some(metadata) = self.metadata.visible_subtree(&msg.message.path, visible) {
+            msg.client_addr.do_send(ActionSuccessResponse::GetMetadata {
+                request_id: msg.message.request_id,
+                metadata,
+                timestamp: unix_timestamp_ms(),
+            });
+        } else {
+            msg.client_addr.do_send(ActionErrorResponse::GetMetadata {
+                request_id: msg.message.request_id,
+                timestamp: unix_timestamp_ms(),
+                error: NOT_FOUND_INVALID_PATH.into(),
+            });
+        }
+    }
+}
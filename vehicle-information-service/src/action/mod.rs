@@ -6,18 +6,26 @@
 //! state of a signal during a Get request and return the response to the client.
 //! Another example is: removing all subscriptions during an UnsubscribeAll.
 
-use actix::prelude::*;
-
-use crate::api_type::ClientConnectionId;
-use crate::router::ClientSession;
+use crate::access_control::Access;
+use crate::api_error::KnownError;
+use crate::api_type::{ActionPath, ClientConnectionId};
+use crate::router::ClientAddr;
+use crate::signal_manager::SignalManager;
 
+pub mod authorize;
 pub mod get;
+pub mod get_metadata;
+pub mod resume;
 pub mod set;
 pub mod subscribe;
 pub mod unsubscribe;
 pub mod unsubscribe_all;
 
-pub use get::Get;
+pub(crate) use authorize::ConnectionInfo;
+pub use authorize::{Authorization, Authorizer, PeerCredentials};
+pub use get::{AddGetRecipient, Get, GetRequest};
+pub use get_metadata::GetMetadata;
+pub use resume::Resume;
 pub use set::{AddSetRecipient, Set};
 pub use subscribe::Subscribe;
 pub use unsubscribe::Unsubscribe;
@@ -28,7 +36,23 @@ pub struct ClientMessage<T> {
     /// Client connection identifier, mostly for logging.
     pub client_connection_id: ClientConnectionId,
     /// Client address for responding to the client.
-    pub client_addr: Addr<ClientSession>,
+    pub client_addr: ClientAddr,
+    /// Authorization of the client session, `None` if the client is not authorized.
+    pub authorization: Option<Authorization>,
     /// Message the client send to the server.
     pub message: T,
 }
+
+impl<T> ClientMessage<T> {
+    /// Check the client's access to the path against the `SignalManager` access control list.
+    pub(crate) fn check_access(
+        &self,
+        signal_manager: &SignalManager,
+        path: &ActionPath,
+        access: Access,
+    ) -> Result<(), KnownError> {
+        signal_manager
+            .access_control
+            .check(self.authorization.as_ref(), path, access)
+    }
+}
@@ -0,0 +1,332 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! Keep client subscriptions across reconnects.
+//! A client session that requested a resume token keeps its subscriptions for the resume grace period
+//! after disconnecting. Resuming with the token from a new session moves the subscriptions, including
+//! their subscription ids and filter state, to the new session.
+//! Subscriptions the new session has no access to, or that exceed its subscription limit,
+//! are removed and reported with a subscription notification error.
+//!
+
+use actix::prelude::*;
+use uuid::Uuid;
+
+use crate::access_control::Access;
+use crate::action::ClientMessage;
+use crate::api_error::{
+    new_resume_error, ActionError, ActionErrorResponse, NOT_FOUND_INVALID_RESUME_TOKEN,
+};
+use crate::api_type::{ActionSuccessResponse, ReqID, ResumeToken, SubscriptionID};
+use crate::router::ClientAddr;
+use crate::signal_manager::{AttachClient, SignalManager};
+use crate::unix_timestamp_ms;
+
+#[cfg(test)]
+mod tests {
+    use crate::access_control::AccessControl;
+    use crate::action::resume::*;
+    use crate::action::{Authorization, Subscribe};
+    use crate::codec::Codec;
+    use crate::outbound::{Outbox, SlowConsumerPolicy};
+    use crate::signal_manager::GetSubscriptionUsage;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    fn client_addr() -> ClientAddr {
+        let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+        ClientAddr::new(Uuid::new_v4(), outbox, Codec::Legacy)
+    }
+
+    fn subscription_ids() -> Vec<SubscriptionID> {
+        vec![
+            SubscriptionID::SubscriptionIDInt(1),
+            SubscriptionID::SubscriptionIDInt(2),
+        ]
+    }
+
+    #[test]
+    fn take_subscriptions_of_detached_session() {
+        let mut signal_manager = SignalManager::default();
+        let resume_token = Uuid::new_v4();
+        signal_manager
+            .detached_subscription_ids
+            .insert(resume_token, subscription_ids());
+
+        assert_eq!(
+            Some(subscription_ids()),
+            signal_manager.take_subscriptions(&resume_token)
+        );
+        // A token can only be used once
+        assert_eq!(None, signal_manager.take_subscriptions(&resume_token));
+    }
+
+    #[test]
+    fn take_subscriptions_of_connected_session() {
+        let mut signal_manager = SignalManager::default();
+        let resume_token = Uuid::new_v4();
+        let previous_client_addr = client_addr();
+        signal_manager
+            .resume_tokens
+            .insert(previous_client_addr.clone(), resume_token);
+        signal_manager
+            .addr_to_subscription_ids
+            .insert(previous_client_addr.clone(), subscription_ids());
+
+        assert_eq!(
+            Some(subscription_ids()),
+            signal_manager.take_subscriptions(&resume_token)
+        );
+        assert!(signal_manager.resume_tokens.is_empty());
+        assert!(!signal_manager
+            .addr_to_subscription_ids
+            .contains_key(&previous_client_addr));
+    }
+
+    async fn recv(outbox: &Outbox) -> serde_json::Value {
+        let outbound = outbox.recv().await.unwrap();
+        serde_json::from_str(&outbound.into_text()).unwrap()
+    }
+
+    #[test]
+    fn resume_drops_subscriptions_without_access() {
+        System::new("resume").block_on(async {
+            let signal_manager = SignalManager {
+                access_control: AccessControl::default().allow(
+                    "oem",
+                    "Vehicle.Cabin".into(),
+                    Access::Read,
+                ),
+                ..Default::default()
+            }
+            .start();
+            let oem = Authorization {
+                scope: "oem".to_string(),
+                expires_at: Instant::now() + Duration::from_secs(60),
+            };
+
+            let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+            let previous_client_addr =
+                ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::Legacy);
+            signal_manager.do_send(ClientMessage {
+                client_connection_id: previous_client_addr.client_connection_id(),
+                client_addr: previous_client_addr.clone(),
+                authorization: Some(oem.clone()),
+                message: Subscribe {
+                    path: "Vehicle.Cabin.Light".into(),
+                    request_id: ReqID::default(),
+                    filters: None,
+                    initial_value: None,
+                },
+            });
+            signal_manager.do_send(ClientMessage {
+                client_connection_id: previous_client_addr.client_connection_id(),
+                client_addr: previous_client_addr,
+                authorization: Some(oem),
+                message: Resume {
+                    request_id: ReqID::default(),
+                    resume_token: None,
+                    replay: false,
+                },
+            });
+            let subscription_id = recv(&outbox).await["subscriptionId"].clone();
+            let resume_token: ResumeToken =
+                serde_json::from_value(recv(&outbox).await["resumeToken"].clone()).unwrap();
+
+            // Resumed by a session that is not authorized
+            let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+            let client_addr = ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::Legacy);
+            signal_manager.do_send(ClientMessage {
+                client_connection_id: client_addr.client_connection_id(),
+                client_addr,
+                authorization: None,
+                message: Resume {
+                    request_id: ReqID::default(),
+                    resume_token: Some(resume_token),
+                    replay: true,
+                },
+            });
+            let notification = recv(&outbox).await;
+            assert_eq!(subscription_id, notification["subscriptionId"]);
+            assert_eq!(json!("user_unknown"), notification["error"]["reason"]);
+            let response = recv(&outbox).await;
+            assert_eq!(json!([]), response["subscriptionIds"]);
+
+            let usage = signal_manager.send(GetSubscriptionUsage).await.unwrap();
+            assert_eq!(0, usage.total);
+        });
+    }
+
+    #[test]
+    fn take_subscriptions_unknown_token() {
+        let mut signal_manager = SignalManager::default();
+        signal_manager
+            .resume_tokens
+            .insert(client_addr(), Uuid::new_v4());
+        assert_eq!(None, signal_manager.take_subscriptions(&Uuid::new_v4()));
+    }
+}
+
+///
+/// Request a resume token or resume the subscriptions of a previous session.
+///
+#[derive(Debug)]
+pub struct Resume {
+    pub request_id: ReqID,
+    /// Resume token of the previous session, `None` to only request a token for this session
+    pub resume_token: Option<ResumeToken>,
+    /// Send the latest value of each resumed subscription
+    pub replay: bool,
+}
+
+impl Message for ClientMessage<Resume> {
+    type Result = ();
+}
+
+impl Handler<ClientMessage<Resume>> for SignalManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClientMessage<Resume>, _ctx: &mut Self::Context) {
+        if let Some(ref resume_token) = msg.message.resume_token {
+            let subscription_ids = match self.take_subscriptions(resume_token) {
+                Some(subscription_ids) => subscription_ids,
+                None => {
+                    msg.client_addr.do_send(new_resume_error(
+                        msg.message.request_id,
+                        NOT_FOUND_INVALID_RESUME_TOKEN.into(),
+                    ));
+                    return;
+                }
+            };
+
+            info!(
+                "Client {} resumed {} subscriptions",
+                msg.client_connection_id,
+                subscription_ids.len()
+            );
+            for subscription_id in subscription_ids {
+                let path = match self.subscription_id_to_subscription.get(&subscription_id) {
+                    Some((_subscription_addr, _client_addr, path)) => path.clone(),
+                    None => continue,
+                };
+
+                // The new session may be authorized for a different scope than the previous session
+                let checked = msg
+                    .check_access(self, &path, Access::Read)
+                    .map_err(ActionError::from)
+                    .and_then(|()| self.check_client_subscription_limit(&msg.client_addr));
+                if let Err(error) = checked {
+                    warn!(
+                        "Dropping subscription {} to path {} on resume of client {}: {}",
+                        subscription_id, path, msg.client_connection_id, error.message
+                    );
+                    self.remove_subscriptions(vec![subscription_id]);
+                    msg.client_addr
+                        .do_send(ActionErrorResponse::SubscriptionNotification {
+                            subscription_id,
+                            error,
+                            timestamp: unix_timestamp_ms(),
+                        });
+                    continue;
+                }
+
+                if let Some((subscription_addr, client_addr, _path)) = self
+                    .subscription_id_to_subscription
+                    .get_mut(&subscription_id)
+                {
+                    *client_addr = msg.client_addr.clone();
+                    subscription_addr.do_send(AttachClient {
+                        client_addr: Some(msg.client_addr.clone()),
+                        replay: msg.message.replay,
+                    });
+                    self.addr_to_subscription_ids
+                        .entry(msg.client_addr.clone())
+                        .or_default()
+                        .push(subscription_id);
+                }
+            }
+        }
+
+        // Tokens are not reused after resuming, a new token is issued for the new session
+        let resume_token = *self
+            .resume_tokens
+            .entry(msg.client_addr.clone())
+            .or_insert_with(Uuid::new_v4);
+
+        msg.client_addr.do_send(ActionSuccessResponse::Resume {
+            request_id: msg.message.request_id,
+            resume_token,
+            subscription_ids: self
+                .addr_to_subscription_ids
+                .get(&msg.client_addr)
+                .cloned()
+                .unwrap_or_default(),
+            timestamp: unix_timestamp_ms(),
+        });
+    }
+}
+
+impl SignalManager {
+    ///
+    /// Keep the subscriptions of a disconnected client for the resume grace period.
+    /// Notifications are not sent until the subscriptions are resumed.
+    ///
+    pub(crate) fn detach_client(
+        &mut self,
+        client_addr: &ClientAddr,
+        resume_token: ResumeToken,
+        ctx: &mut Context<Self>,
+    ) {
+        let subscription_ids = self
+            .addr_to_subscription_ids
+            .remove(client_addr)
+            .unwrap_or_default();
+        for subscription_id in &subscription_ids {
+            if let Some((subscription_addr, _client_addr, _path)) =
+                self.subscription_id_to_subscription.get(subscription_id)
+            {
+                subscription_addr.do_send(AttachClient {
+                    client_addr: None,
+                    replay: false,
+                });
+            }
+        }
+
+        debug!(
+            "Keeping {} subscriptions for {:?} to be resumed",
+            subscription_ids.len(),
+            self.config.resume_grace_period
+        );
+        self.detached_subscription_ids
+            .insert(resume_token, subscription_ids);
+
+        ctx.run_later(self.config.resume_grace_period, move |act, _ctx| {
+            if let Some(subscription_ids) = act.detached_subscription_ids.remove(&resume_token) {
+                debug!("Resume grace period expired");
+                act.remove_subscriptions(subscription_ids);
+            }
+        });
+    }
+
+    ///
+    /// Take the subscriptions of the session with the given resume token.
+    /// The session may still be connected, if the server did not notice the disconnect yet.
+    ///
+    fn take_subscriptions(&mut self, resume_token: &ResumeToken) -> Option<Vec<SubscriptionID>> {
+        if let Some(subscription_ids) = self.detached_subscription_ids.remove(resume_token) {
+            return Some(subscription_ids);
+        }
+
+        let client_addr = self
+            .resume_tokens
+            .iter()
+            .find(|(_client_addr, token)| *token == resume_token)
+            .map(|(client_addr, _token)| client_addr.clone())?;
+        self.resume_tokens.remove(&client_addr);
+        Some(
+            self.addr_to_subscription_ids
+                .remove(&client_addr)
+                .unwrap_or_default(),
+        )
+    }
+}
@@ -5,9 +5,11 @@
 //! new Set receivers.
 //!
 
+use crate::access_control::Access;
 use crate::action::ClientMessage;
 use crate::api_error::{
-    ActionErrorResponse, KnownError, NOT_FOUND_INVALID_PATH, SERVICE_UNAVAILABLE,
+    ActionErrorResponse, KnownError, BAD_REQUEST, GATEWAY_TIMEOUT, NOT_FOUND_INVALID_PATH,
+    SERVICE_UNAVAILABLE,
 };
 use crate::api_type::ReqID;
 use crate::signal_manager::SignalManager;
@@ -18,9 +20,137 @@ use serde_json::Value;
 use crate::api_type::{ActionPath, ActionSuccessResponse};
 use crate::unix_timestamp_ms;
 
+#[cfg(test)]
+mod tests {
+    use crate::action::set::*;
+    use crate::api_error::BAD_REQUEST;
+    use crate::codec::Codec;
+    use crate::config::Config;
+    use crate::outbound::{Outbox, SlowConsumerPolicy};
+    use crate::router::ClientAddr;
+    use serde_json::json;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    /// Recipient that responds with `result`, or never responds if `result` is `None`.
+    struct Recipient {
+        result: Option<Result<(), KnownError>>,
+    }
+
+    impl Actor for Recipient {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<Set> for Recipient {
+        type Result = ResponseFuture<Result<(), KnownError>>;
+
+        fn handle(&mut self, _msg: Set, _ctx: &mut Self::Context) -> Self::Result {
+            match self.result {
+                Some(result) => Box::pin(futures::future::ready(result)),
+                None => Box::pin(futures::future::pending()),
+            }
+        }
+    }
+
+    /// Recipient that has already stopped.
+    struct StoppedRecipient;
+
+    impl Actor for StoppedRecipient {
+        type Context = Context<Self>;
+
+        fn started(&mut self, ctx: &mut Self::Context) {
+            ctx.stop();
+        }
+    }
+
+    impl Handler<Set> for StoppedRecipient {
+        type Result = Result<(), KnownError>;
+
+        fn handle(&mut self, _msg: Set, _ctx: &mut Self::Context) -> Self::Result {
+            Ok(())
+        }
+    }
+
+    /// Send a set request to a `SignalManager` with the given recipient and return the response to the client.
+    fn set<F>(recipient: F) -> Value
+    where
+        F: FnOnce() -> actix::Recipient<Set> + 'static,
+    {
+        System::new("set").block_on(async move {
+            let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+            let client_addr = ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::Legacy);
+            let path: ActionPath = "Vehicle.Cabin.Light".into();
+            let mut signal_manager = SignalManager::new(Config {
+                set_timeout: Duration::from_millis(50),
+                ..Default::default()
+            });
+            signal_manager
+                .set_recipients
+                .insert(path.clone(), recipient());
+
+            signal_manager
+                .start()
+                .send(ClientMessage {
+                    client_connection_id: client_addr.client_connection_id(),
+                    client_addr,
+                    authorization: None,
+                    message: Set {
+                        path,
+                        value: json!(true),
+                        request_id: ReqID::default(),
+                    },
+                })
+                .await
+                .unwrap();
+            let response = outbox.recv().await.unwrap();
+            serde_json::from_str(&response.into_text()).unwrap()
+        })
+    }
+
+    #[test]
+    fn set_recipient_ok() {
+        let response = set(|| {
+            Recipient {
+                result: Some(Ok(())),
+            }
+            .start()
+            .recipient()
+        });
+        assert_eq!(json!("set"), response["action"]);
+        assert!(response.get("error").is_none());
+    }
+
+    #[test]
+    fn set_recipient_error() {
+        let response = set(|| {
+            Recipient {
+                result: Some(Err(BAD_REQUEST)),
+            }
+            .start()
+            .recipient()
+        });
+        assert_eq!(json!("bad_request"), response["error"]["reason"]);
+    }
+
+    #[test]
+    fn set_recipient_timeout() {
+        let response = set(|| Recipient { result: None }.start().recipient());
+        assert_eq!(json!("gateway_timeout"), response["error"]["reason"]);
+    }
+
+    #[test]
+    fn set_recipient_stopped() {
+        let response = set(|| StoppedRecipient.start().recipient());
+        assert_eq!(json!("service_unavailable"), response["error"]["reason"]);
+    }
+}
+
 /// SET request
 /// https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#dfn-setrequest
 ///
+/// The client receives the result of the recipient registered for the path,
+/// or a `GATEWAY_TIMEOUT` error if the recipient does not respond within `Config::set_timeout`.
+///
 #[derive(Clone, Debug)]
 pub struct Set {
     pub path: ActionPath,
@@ -39,32 +169,72 @@ impl Message for ClientMessage<Set> {
 impl Handler<ClientMessage<Set>> for SignalManager {
     type Result = ();
 
-    fn handle(&mut self, msg: ClientMessage<Set>, _ctx: &mut Self::Context) {
-        let recipients = self.set_recipients.clone();
-        if let Some(recipient) = recipients.get(&msg.message.path) {
-            let set_message = msg.message.clone();
-            if let Err(e) = recipient.do_send(set_message) {
-                warn!("Failed to deliver Set message to recipient: {}", e);
+    fn handle(&mut self, msg: ClientMessage<Set>, ctx: &mut Self::Context) {
+        if let Err(error) = msg.check_access(self, &msg.message.path, Access::Write) {
+            msg.client_addr.do_send(ActionErrorResponse::Set {
+                request_id: msg.message.request_id,
+                timestamp: unix_timestamp_ms(),
+                error: error.into(),
+            });
+            return;
+        }
+
+        if let Some(node) = self.metadata.node(&msg.message.path) {
+            if let Err(message) = node.validate(&msg.message.value) {
+                debug!("Rejecting set for path {}: {}", msg.message.path, message);
                 msg.client_addr.do_send(ActionErrorResponse::Set {
                     request_id: msg.message.request_id,
                     timestamp: unix_timestamp_ms(),
-                    error: SERVICE_UNAVAILABLE.into(),
+                    error: BAD_REQUEST.with_message(message),
                 });
                 return;
             }
+        }
+
+        let recipient = match self.set_recipients.get(&msg.message.path) {
+            Some(recipient) => recipient.clone(),
+            None => {
+                // No recipient for the requested path
+                msg.client_addr.do_send(ActionErrorResponse::Set {
+                    request_id: msg.message.request_id,
+                    timestamp: unix_timestamp_ms(),
+                    error: NOT_FOUND_INVALID_PATH.into(),
+                });
+                return;
+            }
+        };
+
+        // Respond once the recipient has handled the request, without blocking the SignalManager.
+        let request = recipient
+            .send(msg.message.clone())
+            .timeout(self.config.set_timeout);
+        ctx.spawn(request.into_actor(self).map(move |result, _act, _ctx| {
+            let request_id = msg.message.request_id;
+            let error = match result {
+                Ok(Ok(())) => {
+                    msg.client_addr.do_send(ActionSuccessResponse::Set {
+                        request_id,
+                        timestamp: unix_timestamp_ms(),
+                    });
+                    return;
+                }
+                Ok(Err(error)) => error,
+                Err(MailboxError::Timeout) => {
+                    warn!("Set recipient for path {} timed out", msg.message.path);
+                    GATEWAY_TIMEOUT
+                }
+                Err(MailboxError::Closed) => {
+                    warn!("Set recipient for path {} is closed", msg.message.path);
+                    SERVICE_UNAVAILABLE
+                }
+            };
 
-            msg.client_addr.do_send(ActionSuccessResponse::Set {
-                request_id: msg.message.request_id,
-                timestamp: unix_timestamp_ms(),
-            });
-        } else {
-            // No recipient for the requested path
             msg.client_addr.do_send(ActionErrorResponse::Set {
-                request_id: msg.message.request_id,
+                request_id,
                 timestamp: unix_timestamp_ms(),
-                error: NOT_FOUND_INVALID_PATH.into(),
+                error: error.into(),
             });
-        }
+        }));
     }
 }
 
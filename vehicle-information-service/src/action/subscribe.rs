@@ -3,11 +3,82 @@
 use actix::prelude::*;
 use uuid::Uuid;
 
+use crate::access_control::Access;
 use crate::action::ClientMessage;
+use crate::api_error::{
+    ActionError, ActionErrorResponse, BAD_REQUEST_FILTER_INVALID, SERVICE_UNAVAILABLE,
+    TOO_MANY_REQUESTS,
+};
 use crate::api_type::*;
+use crate::filter;
+use crate::router::ClientAddr;
 use crate::signal_manager::{SignalManager, Subscription};
 use crate::unix_timestamp_ms;
 
+#[cfg(test)]
+mod tests {
+    use crate::action::subscribe::*;
+    use crate::codec::Codec;
+    use crate::config::Config;
+    use crate::outbound::{Outbox, SlowConsumerPolicy};
+
+    fn client_addr() -> ClientAddr {
+        let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+        ClientAddr::new(Uuid::new_v4(), outbox, Codec::Legacy)
+    }
+
+    fn subscribe(signal_manager: &mut SignalManager, client_addr: &ClientAddr, path: &str) {
+        let subscription_id = SubscriptionID::SubscriptionIDUUID(Uuid::new_v4());
+        signal_manager
+            .addr_to_subscription_ids
+            .entry(client_addr.clone())
+            .or_default()
+            .push(subscription_id);
+        signal_manager
+            .subscription_index
+            .insert(&path.into(), subscription_id);
+    }
+
+    #[test]
+    fn check_subscription_limits_per_client() {
+        let mut signal_manager = SignalManager::new(Config {
+            max_subscriptions_per_client: Some(2),
+            ..Default::default()
+        });
+        let client = client_addr();
+        let path = "Signal.Body.Trunk".into();
+        subscribe(&mut signal_manager, &client, "Signal.Body");
+        assert!(signal_manager
+            .check_subscription_limits(&client, &path)
+            .is_ok());
+
+        subscribe(&mut signal_manager, &client, "Signal.Cabin");
+        let error = signal_manager
+            .check_subscription_limits(&client, &path)
+            .unwrap_err();
+        assert_eq!("too_many_requests", error.reason);
+        assert!(signal_manager
+            .check_subscription_limits(&client_addr(), &path)
+            .is_ok());
+    }
+
+    #[test]
+    fn check_subscription_limits_per_path() {
+        let mut signal_manager = SignalManager::new(Config {
+            max_subscriptions_per_path: Some(1),
+            ..Default::default()
+        });
+        subscribe(&mut signal_manager, &client_addr(), "Signal.Body");
+        let error = signal_manager
+            .check_subscription_limits(&client_addr(), &"signal.body".into())
+            .unwrap_err();
+        assert_eq!("service_unavailable", error.reason);
+        assert!(signal_manager
+            .check_subscription_limits(&client_addr(), &"Signal.Body.Trunk".into())
+            .is_ok());
+    }
+}
+
 ///
 /// SUBSCRIBE request
 /// [Subscribe](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#subscribe)
@@ -17,6 +88,8 @@ pub struct Subscribe {
     pub path: ActionPath,
     pub request_id: ReqID,
     pub filters: Option<Filters>,
+    /// Send the cached value as the first notification, `None` for `Config::initial_value`
+    pub initial_value: Option<bool>,
 }
 
 impl Message for ClientMessage<Subscribe> {
@@ -27,20 +100,68 @@ impl Handler<ClientMessage<Subscribe>> for SignalManager {
     type Result = ();
 
     fn handle(&mut self, msg: ClientMessage<Subscribe>, _ctx: &mut Self::Context) {
+        if let Err(error) = msg.check_access(self, &msg.message.path, Access::Read) {
+            msg.client_addr.do_send(ActionErrorResponse::Subscribe {
+                request_id: msg.message.request_id,
+                timestamp: unix_timestamp_ms(),
+                error: error.into(),
+            });
+            return;
+        }
+
+        if let Err(error) = self.check_subscription_limits(&msg.client_addr, &msg.message.path) {
+            warn!(
+                "Rejecting subscribe of client {} for path {}: {}",
+                msg.client_connection_id, msg.message.path, error.message
+            );
+            msg.client_addr.do_send(ActionErrorResponse::Subscribe {
+                request_id: msg.message.request_id,
+                timestamp: unix_timestamp_ms(),
+                error,
+            });
+            return;
+        }
+
+        let mut filters = msg.message.filters;
+        if let Some(filters) = filters.as_mut() {
+            let metadata = self.metadata.node(&msg.message.path);
+            if let Err(message) = filter::validate(filters, metadata) {
+                debug!(
+                    "Rejecting subscribe for path {}: {}",
+                    msg.message.path, message
+                );
+                msg.client_addr.do_send(ActionErrorResponse::Subscribe {
+                    request_id: msg.message.request_id,
+                    timestamp: unix_timestamp_ms(),
+                    error: BAD_REQUEST_FILTER_INVALID.with_message(message),
+                });
+                return;
+            }
+
+            // Protect the server from clients requesting very short intervals
+            let min_interval = self.config.min_interval.as_millis() as u64;
+            filters.interval = filters.interval.map(|interval| interval.max(min_interval));
+        }
+
         let subscription_id = SubscriptionID::SubscriptionIDUUID(Uuid::new_v4());
         debug!(
             "Adding subscriber with id {} to path: {}",
             subscription_id, msg.message.path
         );
 
-        let subscription = Subscription {
-            client_addr: msg.client_addr.clone(),
+        let mut subscription = Subscription {
+            client_addr: Some(msg.client_addr.clone()),
             path: msg.message.path.clone(),
             subscription_id,
-            filters: msg.message.filters,
-            latest_signal_value: None,
-            last_signal_value_client: None,
+            filters,
+            latest_signal_value: Default::default(),
+            last_signal_value_client: Default::default(),
             interval_handle: None,
+            coalesce_pending: Default::default(),
+            coalesce_handle: None,
+            invalid_filter_paths: Default::default(),
+            filter_states: Default::default(),
+            sequence: 0,
         };
 
         if let Some(subscriptions) = self.addr_to_subscription_ids.get_mut(&msg.client_addr) {
@@ -50,6 +171,29 @@ impl Handler<ClientMessage<Subscribe>> for SignalManager {
                 .insert(msg.client_addr.clone(), vec![subscription_id]);
         }
 
+        let response = ActionSuccessResponse::Subscribe {
+            request_id: msg.message.request_id,
+            subscription_id,
+            timestamp: unix_timestamp_ms(),
+        };
+        msg.client_addr.do_send(response);
+
+        // Sent after the subscribe response
+        if msg
+            .message
+            .initial_value
+            .unwrap_or(self.config.initial_value)
+        {
+            let subscription_path = &msg.message.path;
+            subscription.latest_signal_value = self
+                .signal_cache
+                .iter()
+                .filter(|(path, cached)| cached.available && subscription_path.matches(path))
+                .map(|(path, cached)| (path.clone(), (cached.timestamp, cached.value.clone())))
+                .collect();
+            subscription.send_latest_values();
+        }
+
         let addr = subscription.start();
 
         self.subscription_id_to_subscription.insert(
@@ -57,19 +201,61 @@ impl Handler<ClientMessage<Subscribe>> for SignalManager {
             (addr, msg.client_addr.clone(), msg.message.path.clone()),
         );
 
-        if let Some(subscriptions) = self.path_to_subscription_id.get_mut(&msg.message.path) {
-            subscriptions.push(subscription_id);
-        } else {
-            self.path_to_subscription_id
-                .insert(msg.message.path, vec![subscription_id]);
+        self.subscription_index
+            .insert(&msg.message.path, subscription_id);
+    }
+}
+
+impl SignalManager {
+    /// Check the configured subscription limits before adding a subscription of the client to the path.
+    fn check_subscription_limits(
+        &self,
+        client_addr: &ClientAddr,
+        path: &ActionPath,
+    ) -> Result<(), ActionError> {
+        self.check_client_subscription_limit(client_addr)?;
+
+        // Only subscriptions to exactly this path count, see `Config::max_subscriptions_per_path`
+        if let Some(max) = self.config.max_subscriptions_per_path {
+            if self.subscription_index.count(path) >= max {
+                return Err(SERVICE_UNAVAILABLE.with_message(format!(
+                    "The path {} exceeds the limit of {} subscriptions.",
+                    path, max
+                )));
+            }
         }
 
-        let response = ActionSuccessResponse::Subscribe {
-            request_id: msg.message.request_id,
-            subscription_id,
-            timestamp: unix_timestamp_ms(),
-        };
+        if let Some(max) = self.config.max_subscriptions {
+            if self.subscription_id_to_subscription.len() >= max {
+                return Err(SERVICE_UNAVAILABLE.with_message(format!(
+                    "The server exceeds the limit of {} subscriptions.",
+                    max
+                )));
+            }
+        }
 
-        msg.client_addr.do_send(response);
+        Ok(())
+    }
+
+    /// Check the per client subscription limit before adding a subscription to the client session.
+    pub(crate) fn check_client_subscription_limit(
+        &self,
+        client_addr: &ClientAddr,
+    ) -> Result<(), ActionError> {
+        if let Some(max) = self.config.max_subscriptions_per_client {
+            let count = self
+                .addr_to_subscription_ids
+                .get(client_addr)
+                .map(Vec::len)
+                .unwrap_or_default();
+            if count >= max {
+                return Err(TOO_MANY_REQUESTS.with_message(format!(
+                    "The client exceeds the limit of {} subscriptions.",
+                    max
+                )));
+            }
+        }
+
+        Ok(())
     }
 }
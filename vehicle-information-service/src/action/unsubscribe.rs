@@ -59,9 +59,8 @@ impl Handler<ClientMessage<Unsubscribe>> for SignalManager {
                 subscriptions.retain(|sub| *sub != msg.message.subscription_id)
             }
 
-            if let Some(subscription_ids) = self.path_to_subscription_id.get_mut(&path) {
-                subscription_ids.retain(|sub| *sub != msg.message.subscription_id)
-            }
+            self.subscription_index
+                .remove(&path, &msg.message.subscription_id);
             debug!(
                 "Removed subscriber with id {} to path: {}",
                 msg.message.subscription_id, path
@@ -3,12 +3,14 @@
 //!
 //! Remove all client subscriptions when a client requests an UnsubscribeAll
 //! or the client disconnects.
+//! Subscriptions of a disconnected client that requested a resume token are kept
+//! until the resume grace period expires.
 //!
 
 use actix::prelude::*;
 
 use crate::action::ClientMessage;
-use crate::api_type::{ActionSuccessResponse, ReqID};
+use crate::api_type::{ActionSuccessResponse, ReqID, SubscriptionID};
 use crate::signal_manager::{SignalManager, StopSubscription};
 use crate::unix_timestamp_ms;
 
@@ -17,6 +19,7 @@ use crate::unix_timestamp_ms;
 ///
 #[derive(Debug)]
 pub struct UnsubscribeAll {
+    /// `None` if the client disconnected
     pub request_id: Option<ReqID>,
 }
 
@@ -27,27 +30,20 @@ impl Message for ClientMessage<UnsubscribeAll> {
 impl Handler<ClientMessage<UnsubscribeAll>> for SignalManager {
     type Result = ();
 
-    fn handle(&mut self, msg: ClientMessage<UnsubscribeAll>, _ctx: &mut Self::Context) {
-        for subscription_id in self
-            .addr_to_subscription_ids
-            .get(&msg.client_addr)
-            .unwrap_or(&Vec::new())
-        {
-            if let Some((subscription_addr, _client_session_addr, path)) =
-                self.subscription_id_to_subscription.get(&subscription_id)
-            {
-                subscription_addr.do_send(StopSubscription {});
-
-                if let Some(subscription_ids) = self.path_to_subscription_id.get_mut(&path) {
-                    subscription_ids.retain(|sub| sub != subscription_id)
-                }
-                debug!(
-                    "Removed subscription with id {} to path: {}",
-                    subscription_id, path
-                );
+    fn handle(&mut self, msg: ClientMessage<UnsubscribeAll>, ctx: &mut Self::Context) {
+        if msg.message.request_id.is_none() {
+            if let Some(resume_token) = self.resume_tokens.remove(&msg.client_addr) {
+                self.detach_client(&msg.client_addr, resume_token, ctx);
+                return;
             }
         }
 
+        let subscription_ids = self
+            .addr_to_subscription_ids
+            .remove(&msg.client_addr)
+            .unwrap_or_default();
+        self.remove_subscriptions(subscription_ids);
+
         if let Some(request_id) = msg.message.request_id {
             let response = ActionSuccessResponse::UnsubscribeAll {
                 request_id,
@@ -57,3 +53,22 @@ impl Handler<ClientMessage<UnsubscribeAll>> for SignalManager {
         }
     }
 }
+
+impl SignalManager {
+    pub(crate) fn remove_subscriptions(&mut self, subscription_ids: Vec<SubscriptionID>) {
+        for subscription_id in subscription_ids {
+            if let Some((subscription_addr, _client_session_addr, path)) = self
+                .subscription_id_to_subscription
+                .remove(&subscription_id)
+            {
+                subscription_addr.do_send(StopSubscription {});
+
+                self.subscription_index.remove(&path, &subscription_id);
+                debug!(
+                    "Removed subscription with id {} to path: {}",
+                    subscription_id, path
+                );
+            }
+        }
+    }
+}
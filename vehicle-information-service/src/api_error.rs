@@ -275,6 +275,19 @@ pub enum ActionErrorResponse {
         #[serde(skip_deserializing, rename = "timestamp")]
         timestamp: u128,
     },
+    ///
+    /// Error response for failed RESUME request
+    ///
+    Resume {
+        #[serde(rename = "requestId")]
+        request_id: ReqID,
+        #[serde(rename = "error")]
+        error: ActionError,
+        /// can currently not be deserialized, serde_json arbitrary precision bug
+        /// https://github.com/serde-rs/json/issues/505
+        #[serde(skip_deserializing, rename = "timestamp")]
+        timestamp: u128,
+    },
 }
 
 impl From<io::Error> for ActionErrorResponse {
@@ -294,6 +307,24 @@ impl fmt::Display for ActionErrorResponse {
     }
 }
 
+impl ActionErrorResponse {
+    /// Error of the response, regardless of the action.
+    pub fn error(&self) -> &ActionError {
+        match self {
+            ActionErrorResponse::Authorize { error, .. }
+            | ActionErrorResponse::GetMetadata { error, .. }
+            | ActionErrorResponse::Get { error, .. }
+            | ActionErrorResponse::Set { error, .. }
+            | ActionErrorResponse::Subscribe { error, .. }
+            | ActionErrorResponse::Subscription { error, .. }
+            | ActionErrorResponse::SubscriptionNotification { error, .. }
+            | ActionErrorResponse::Unsubscribe { error, .. }
+            | ActionErrorResponse::UnsubscribeAll { error, .. }
+            | ActionErrorResponse::Resume { error, .. } => error,
+        }
+    }
+}
+
 pub fn new_get_error(request_id: ReqID, error: ActionError) -> ActionErrorResponse {
     ActionErrorResponse::Get {
         request_id,
@@ -355,6 +386,14 @@ pub fn new_authorize_error(request_id: ReqID, error: ActionError) -> ActionError
     }
 }
 
+pub fn new_resume_error(request_id: ReqID, error: ActionError) -> ActionErrorResponse {
+    ActionErrorResponse::Resume {
+        request_id,
+        error,
+        timestamp: unix_timestamp_ms(),
+    }
+}
+
 pub fn new_deserialization_error() -> ActionError {
     // TODO this does not appear to be specified in spec
     StatusCode::BAD_REQUEST.into()
@@ -364,8 +403,19 @@ pub fn new_deserialization_error() -> ActionError {
 /// An error that is listed in the specification error table.
 /// [Error Doc](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#errors)
 ///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct KnownError(StatusCode, &'static str, &'static str);
 
+impl KnownError {
+    /// Error with the known number and reason but a more specific message.
+    pub fn with_message(self, message: String) -> ActionError {
+        ActionError {
+            message,
+            ..self.into()
+        }
+    }
+}
+
 impl From<KnownError> for ActionError {
     fn from(known_error: KnownError) -> Self {
         Self {
@@ -480,6 +530,18 @@ pub const NOT_FOUND_INVALID_SUBSCRIPTION_ID: KnownError = KnownError(
     "The specified subscription was not found.",
 );
 
+pub const NOT_FOUND_UNAVAILABLE_DATA: KnownError = KnownError(
+    StatusCode::NOT_FOUND,
+    "unavailable_data",
+    "The requested data is currently not available.",
+);
+
+pub const NOT_FOUND_INVALID_RESUME_TOKEN: KnownError = KnownError(
+    StatusCode::NOT_FOUND,
+    "invalid_resume_token",
+    "The resume token is unknown or the grace period of the session expired.",
+);
+
 pub const NOT_ACCEPTABLE: KnownError = KnownError(
     StatusCode::NOT_ACCEPTABLE,
     "not_acceptable",
@@ -60,6 +60,60 @@ mod tests {
         let d_sub_id_uuid = serde_json::from_str(&s_sub_id_uuid).unwrap();
         assert_eq!(sub_id_uuid, d_sub_id_uuid);
     }
+
+    #[test]
+    fn action_path_matches_exact_path() {
+        let path = ActionPath::new("Vehicle.Speed");
+        assert!(path.matches(&ActionPath::new("vehicle.speed")));
+        assert!(!path.matches(&ActionPath::new("Vehicle.SpeedLimit")));
+        assert!(!path.matches(&ActionPath::new("Vehicle")));
+    }
+
+    #[test]
+    fn action_path_matches_paths_below_branch() {
+        let branch = ActionPath::new("Vehicle.Cabin");
+        assert!(branch.matches(&ActionPath::new("Vehicle.Cabin.Door.Row1.Left.IsOpen")));
+        assert!(!branch.matches(&ActionPath::new("Vehicle.Speed")));
+    }
+
+    #[test]
+    fn action_path_matches_wildcard() {
+        let wildcard = ActionPath::new("Signal.*.RPM");
+        assert!(wildcard.is_wildcard());
+        assert!(wildcard.matches(&ActionPath::new("Signal.Drivetrain.RPM")));
+        assert!(!wildcard.matches(&ActionPath::new("Signal.Drivetrain.Speed")));
+        assert!(!wildcard.matches(&ActionPath::new("Signal.RPM")));
+
+        let trailing = ActionPath::new("Vehicle.Cabin.*");
+        assert!(trailing.matches(&ActionPath::new("Vehicle.Cabin.Door.Row1.Left.IsOpen")));
+        assert!(!trailing.matches(&ActionPath::new("Vehicle.Cabin")));
+    }
+
+    #[test]
+    fn serialize_deserialize_subscription_sequence() {
+        let subscription = ActionSuccessResponse::Subscription {
+            subscription_id: SubscriptionID::SubscriptionIDInt(1),
+            path: None,
+            value: Value::Bool(true),
+            sequence: Some(7),
+            timestamp: 0,
+        };
+        let s_subscription = serde_json::to_string(&subscription).unwrap();
+        match serde_json::from_str(&s_subscription).unwrap() {
+            ActionSuccessResponse::Subscription { sequence, .. } => assert_eq!(Some(7), sequence),
+            response => panic!("Unexpected response {:?}", response),
+        }
+
+        // Notifications without a sequence number remain valid
+        let d_subscription = serde_json::from_str(
+            r#"{ "action": "subscription", "subscriptionId": "1", "value": true, "timestamp": 0 }"#,
+        )
+        .unwrap();
+        match d_subscription {
+            ActionSuccessResponse::Subscription { sequence, .. } => assert_eq!(None, sequence),
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
 }
 
 /// Unique id value specified by the client.
@@ -261,8 +315,42 @@ impl ActionPath {
     pub fn new(path: &str) -> ActionPath {
         ActionPath(path.to_string())
     }
+
+    /// Path segments, e.g. `Vehicle`, `Speed` for `Vehicle.Speed`.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.0.split('.')
+    }
+
+    /// Whether the path contains a `*` wildcard segment.
+    pub fn is_wildcard(&self) -> bool {
+        self.segments().any(|segment| segment == WILDCARD)
+    }
+
+    ///
+    /// Whether `path` is matched when using this path as a pattern.
+    /// A `*` segment matches any single segment and a pattern also matches all paths below it.
+    ///
+    /// # Examples
+    /// `Vehicle.Cabin` and `Vehicle.Cabin.*` match `Vehicle.Cabin.Door.Row1.Left.IsOpen`,
+    /// `Signal.*.RPM` matches `Signal.Drivetrain.RPM`.
+    ///
+    pub fn matches(&self, path: &ActionPath) -> bool {
+        let mut path_segments = path.segments();
+        self.segments().all(|pattern_segment| {
+            path_segments
+                .next()
+                .map(|segment| {
+                    pattern_segment == WILDCARD
+                        || pattern_segment.to_lowercase() == segment.to_lowercase()
+                })
+                .unwrap_or(false)
+        })
+    }
 }
 
+/// Path segment that matches any single segment
+pub const WILDCARD: &str = "*";
+
 impl PartialEq for ActionPath {
     fn eq(&self, other: &Self) -> bool {
         self.0.to_lowercase() == other.0.to_lowercase()
@@ -347,6 +435,12 @@ pub enum ActionType {
     #[serde(alias = "unsubscribeAll")]
     #[serde(alias = "UnsubscribeAll")]
     UnsubscribeAll,
+    ///
+    /// Allows the client to keep its subscriptions across reconnects, not part of the VIS specification.
+    ///
+    #[serde(alias = "resume")]
+    #[serde(alias = "Resume")]
+    Resume,
 }
 
 impl fmt::Display for ActionType {
@@ -360,21 +454,75 @@ impl fmt::Display for ActionType {
             ActionType::Subscription => "SUBSCRIPTION",
             ActionType::Unsubscribe => "UNSUBSCRIBE",
             ActionType::UnsubscribeAll => "UNSUBSCRIBE_ALL",
+            ActionType::Resume => "RESUME",
         };
         write!(f, "{}", msg)
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+///
+/// Whether values inside or outside of a `FilterRange` match.
+/// A notification is sent when a value crosses into the matching side.
+///
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RangeMode {
+    #[default]
+    Inside,
+    Outside,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
 pub struct FilterRange {
+    /// Values equal to the bound are inside the range, unless `below_exclusive` is set
     #[serde(default)]
     pub below: Option<Number>,
+    /// Values equal to the bound are inside the range, unless `above_exclusive` is set
     #[serde(default)]
     pub above: Option<Number>,
+    #[serde(default, rename = "belowExclusive")]
+    pub below_exclusive: bool,
+    #[serde(default, rename = "aboveExclusive")]
+    pub above_exclusive: bool,
+    #[serde(default)]
+    pub mode: RangeMode,
+    /// Once a value matched, the range is only re-armed after the value is back across
+    /// a bound by more than the hysteresis, which avoids flapping around the bounds.
+    /// The bounds at which the range matches are not changed.
+    #[serde(default)]
+    pub hysteresis: Option<Number>,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+///
+/// Transition of a boolean value that triggers a notification.
+///
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Edge {
+    /// `false` to `true`
+    Rising,
+    /// `true` to `false`
+    Falling,
+    /// Any change
+    Both,
+}
+
+///
+/// How the value conditions `range`, `minChange`, `oneOf` and `edge` of `Filters` are combined.
+///
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Combinator {
+    /// All conditions must match
+    #[default]
+    All,
+    /// At least one condition must match
+    Any,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
 pub struct Filters {
+    /// Interval in milliseconds in which the latest value is sent, if it changed.
     #[serde(default)]
     pub interval: Option<u64>,
     #[serde(default)]
@@ -382,6 +530,25 @@ pub struct Filters {
     #[serde(default)]
     #[serde(rename = "minChange")]
     pub min_change: Option<Number>,
+    /// Deliver at most one notification per `coalesce` milliseconds, containing the newest value.
+    /// Unlike `interval`, values are not re-sent if the signal did not change.
+    /// Can not be combined with `interval`.
+    #[serde(default)]
+    pub coalesce: Option<u64>,
+    /// Only notify if the value equals one of the given values, e.g. for enums and strings.
+    #[serde(default)]
+    #[serde(rename = "oneOf")]
+    pub one_of: Option<Vec<Value>>,
+    /// Only notify on the given transition of a boolean value.
+    #[serde(default)]
+    pub edge: Option<Edge>,
+    /// Only send the changed members of object values, as JSON merge patch (RFC 7386).
+    #[serde(default)]
+    #[serde(rename = "deepDiff")]
+    pub deep_diff: Option<bool>,
+    /// Combination of the value conditions, all conditions must match by default.
+    #[serde(default)]
+    pub combine: Combinator,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -445,6 +612,12 @@ pub enum Action {
         #[serde(default)]
         #[serde(skip_serializing_if = "Option::is_none")]
         filters: Option<Filters>,
+        /// Send the current value as the first notification, `None` for the server default.
+        /// Not part of the VIS specification.
+        #[serde(default)]
+        #[serde(rename = "initialValue")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        initial_value: Option<bool>,
     },
     ///
     /// [Unsubscribe Doc](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#unsubscribe)
@@ -466,6 +639,25 @@ pub enum Action {
         #[serde(rename = "requestId")]
         request_id: ReqID,
     },
+    ///
+    /// RESUME request, not part of the VIS specification.
+    /// Without a resume token the session opts in to resuming and a token is returned.
+    /// With the resume token of a previous session, the subscriptions of that session are
+    /// moved to this session if the session disconnected within the resume grace period.
+    ///
+    #[serde(alias = "resume")]
+    #[serde(alias = "Resume")]
+    Resume {
+        #[serde(rename = "requestId")]
+        request_id: ReqID,
+        #[serde(default)]
+        #[serde(rename = "resumeToken")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        resume_token: Option<ResumeToken>,
+        /// Send the latest value of each resumed subscription
+        #[serde(default)]
+        replay: bool,
+    },
 }
 
 impl Message for Action {
@@ -482,6 +674,20 @@ impl fmt::Display for Action {
 #[serde(tag = "action")]
 #[serde(rename_all = "camelCase")]
 pub enum ActionSuccessResponse {
+    ///
+    /// Response for successful AUTHORIZE request
+    /// [Authorize Doc](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#dfn-authorizesuccessresponse)
+    ///
+    Authorize {
+        #[serde(rename = "requestId")]
+        request_id: ReqID,
+        /// Time to live of the authorization in seconds
+        #[serde(rename = "TTL")]
+        ttl: u64,
+        // serde_json currently does not support deserializing u128
+        #[serde(skip_deserializing)]
+        timestamp: u128,
+    },
     ///
     /// Response for successful GET request
     /// [Get Doc](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#dfn-getrequest)
@@ -495,6 +701,19 @@ pub enum ActionSuccessResponse {
         timestamp: u128,
     },
     ///
+    /// Response for successful GetMetadata request
+    /// [Metadata Doc](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#dfn-metadatasuccessresponse)
+    ///
+    GetMetadata {
+        #[serde(rename = "requestId")]
+        request_id: ReqID,
+        /// VSS JSON metadata of the requested path
+        metadata: Value,
+        // serde_json currently does not support deserializing u128
+        #[serde(skip_deserializing)]
+        timestamp: u128,
+    },
+    ///
     /// Response for successful SET request
     /// [Set Doc](https://w3c.github.io/automotive/vehicle_data/vehicle_information_service.html#dfn-setrequest)
     ///
@@ -533,7 +752,14 @@ pub enum ActionSuccessResponse {
     Subscription {
         #[serde(rename = "subscriptionId")]
         subscription_id: SubscriptionID,
+        /// Path of the changed signal, set for branch and wildcard subscriptions
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<ActionPath>,
         value: Value,
+        /// Increases by one with every notification of the subscription,
+        /// a gap means that notifications were dropped
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sequence: Option<u64>,
         // serde_json currently does not support deserializing u128
         #[serde(skip_deserializing)]
         timestamp: u128,
@@ -551,9 +777,30 @@ pub enum ActionSuccessResponse {
         #[serde(skip_deserializing)]
         timestamp: u128,
     },
+    ///
+    /// Response for successful RESUME request
+    ///
+    Resume {
+        #[serde(rename = "requestId")]
+        request_id: ReqID,
+        /// Token to resume the subscriptions of this session after a reconnect
+        #[serde(rename = "resumeToken")]
+        resume_token: ResumeToken,
+        /// Subscriptions of this session, including resumed subscriptions
+        #[serde(rename = "subscriptionIds")]
+        subscription_ids: Vec<SubscriptionID>,
+        // serde_json currently does not support deserializing u128
+        #[serde(skip_deserializing)]
+        timestamp: u128,
+    },
 }
 
 ///
 /// Websocket client connection id
 ///
 pub type ClientConnectionId = uuid::Uuid;
+
+///
+/// Secret token of a client session that allows resuming its subscriptions after a reconnect
+///
+pub type ResumeToken = uuid::Uuid;
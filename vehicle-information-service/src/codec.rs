@@ -0,0 +1,617 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! Message formats of client sessions.
+//! The websocket subprotocol selects the format of each session: `VISSv2` for the VISS v2 format,
+//! `wvss1.0` or no subprotocol for the W3C VIS draft format the actions are defined in.
+//! VISS v2 requests are translated to `Action`s before dispatching, responses and notifications
+//! are serialized to the format of the session when they are queued.
+//!
+
+use actix_web::http::header::SEC_WEBSOCKET_PROTOCOL;
+use actix_web::HttpRequest;
+use http::status::StatusCode;
+use serde::de::Error;
+use serde_json::{from_str, from_value, json, to_string, to_value, Map, Value};
+use std::convert::TryFrom;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::api_error::{new_subscribe_error, ActionErrorResponse};
+use crate::api_type::{Action, ActionPath, ActionSuccessResponse, ReqID, SubscriptionID};
+use crate::outbound::Outbound;
+use crate::serialize_result;
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn negotiate_protocol() {
+        let negotiate = |protocols| {
+            Codec::negotiate(
+                &TestRequest::default()
+                    .header(SEC_WEBSOCKET_PROTOCOL, protocols)
+                    .to_http_request(),
+            )
+        };
+        assert_eq!(Codec::VissV2, negotiate("VISSv2"));
+        assert_eq!(Codec::VissV2, negotiate("unknown, VISSv2, wvss1.0"));
+        assert_eq!(Codec::Legacy, negotiate("wvss1.0, VISSv2"));
+        assert_eq!(Codec::Legacy, negotiate("unknown"));
+        assert_eq!(
+            Codec::Legacy,
+            Codec::negotiate(&TestRequest::default().to_http_request())
+        );
+    }
+
+    #[test]
+    fn decode_viss_v2_get_metadata() {
+        let action = Codec::VissV2
+            .decode(r#"{ "action": "get", "path": "Vehicle/Speed", "requestId": 1, "filter": { "type": "static-metadata" } }"#)
+            .unwrap();
+        match action {
+            Action::GetMetadata { path, request_id } => {
+                assert_eq!(ActionPath::new("Vehicle.Speed"), path);
+                assert_eq!(ReqID::ReqIDInt(1), request_id);
+            }
+            _ => panic!("Unexpected action {}", action),
+        }
+    }
+
+    #[test]
+    fn decode_viss_v2_subscribe_filters() {
+        let action = Codec::VissV2
+            .decode(
+                r#"{ "action": "subscribe", "path": "Vehicle.Speed", "requestId": "2", "filter": [
+                    { "type": "timebased", "parameter": { "period": "100" } },
+                    { "type": "change", "parameter": { "logic-op": "ne", "diff": "5" } },
+                    { "type": "range", "parameter": [
+                        { "logic-op": "gt", "boundary": "10" },
+                        { "logic-op": "lt", "boundary": "20.5" }
+                    ] }
+                ] }"#,
+            )
+            .unwrap();
+        match action {
+            Action::Subscribe { filters, .. } => {
+                let filters = filters.unwrap();
+                assert_eq!(Some(100), filters.interval);
+                assert_eq!(Some(json!(5)), filters.min_change.map(Value::Number));
+                let range = filters.range.unwrap();
+                assert_eq!(Some(json!(10)), range.above.map(Value::Number));
+                assert_eq!(Some(json!(20.5)), range.below.map(Value::Number));
+                assert!(range.above_exclusive);
+                assert!(range.below_exclusive);
+            }
+            _ => panic!("Unexpected action {}", action),
+        }
+    }
+
+    #[test]
+    fn decode_viss_v2_subscribe_inclusive_range() {
+        let action = Codec::VissV2
+            .decode(
+                r#"{ "action": "subscribe", "path": "Vehicle.Speed", "requestId": "2", "filter":
+                    { "type": "range", "parameter": [
+                        { "logic-op": "ge", "boundary": "10" },
+                        { "logic-op": "le", "boundary": "20" }
+                    ] }
+                }"#,
+            )
+            .unwrap();
+        match action {
+            Action::Subscribe { filters, .. } => {
+                let range = filters.unwrap().range.unwrap();
+                assert_eq!(Some(json!(10)), range.above.map(Value::Number));
+                assert_eq!(Some(json!(20)), range.below.map(Value::Number));
+                assert!(!range.above_exclusive);
+                assert!(!range.below_exclusive);
+            }
+            _ => panic!("Unexpected action {}", action),
+        }
+    }
+
+    #[test]
+    fn decode_viss_v2_err_unsupported_filter() {
+        assert!(Codec::VissV2
+            .decode(r#"{ "action": "subscribe", "path": "Vehicle.Speed", "requestId": "3", "filter": { "type": "curvelog" } }"#)
+            .is_err());
+        assert!(Codec::VissV2
+            .decode(r#"{ "action": "set", "path": "Vehicle.Speed", "value": 1, "requestId": "4", "filter": {} }"#)
+            .is_err());
+    }
+
+    fn encode_viss_v2(response: Response) -> Value {
+        from_str(&Codec::VissV2.encode(response).into_text()).unwrap()
+    }
+
+    #[test]
+    fn encode_viss_v2_get() {
+        let response = Response::from(ActionSuccessResponse::Get {
+            request_id: ReqID::ReqIDInt(5),
+            value: json!(42),
+            timestamp: 1_577_836_800_123,
+        })
+        .with_data(vec![DataPoint {
+            path: "Vehicle.Speed".into(),
+            value: json!(42),
+            timestamp: 1_577_836_800_123,
+        }]);
+        assert_eq!(
+            json!({
+                "action": "get",
+                "requestId": "5",
+                "data": {
+                    "path": "Vehicle.Speed",
+                    "dp": { "value": 42, "ts": "2020-01-01T00:00:00.123Z" }
+                },
+                "ts": "2020-01-01T00:00:00.123Z"
+            }),
+            encode_viss_v2(response)
+        );
+    }
+
+    #[test]
+    fn encode_viss_v2_get_of_branch() {
+        let response = Response::from(ActionSuccessResponse::Get {
+            request_id: ReqID::ReqIDInt(5),
+            value: json!({ "Vehicle.Speed": 42, "Vehicle.Cabin.Door.IsOpen": true }),
+            timestamp: 1000,
+        })
+        .with_data(vec![
+            DataPoint {
+                path: "Vehicle.Cabin.Door.IsOpen".into(),
+                value: json!(true),
+                timestamp: 0,
+            },
+            DataPoint {
+                path: "Vehicle.Speed".into(),
+                value: json!(42),
+                timestamp: 1000,
+            },
+        ]);
+        assert_eq!(
+            json!([
+                {
+                    "path": "Vehicle.Cabin.Door.IsOpen",
+                    "dp": { "value": true, "ts": "1970-01-01T00:00:00.000Z" }
+                },
+                {
+                    "path": "Vehicle.Speed",
+                    "dp": { "value": 42, "ts": "1970-01-01T00:00:01.000Z" }
+                }
+            ]),
+            encode_viss_v2(response)["data"]
+        );
+    }
+
+    #[test]
+    fn encode_viss_v2_subscription_of_exact_path() {
+        let response = Response::from(ActionSuccessResponse::Subscription {
+            subscription_id: SubscriptionID::SubscriptionIDInt(8),
+            path: None,
+            value: json!(42),
+            sequence: Some(1),
+            timestamp: 0,
+        })
+        .with_data(vec![DataPoint {
+            path: "Vehicle.Speed".into(),
+            value: json!(42),
+            timestamp: 0,
+        }]);
+        let encoded = encode_viss_v2(response);
+        assert_eq!(json!("Vehicle.Speed"), encoded["data"]["path"]);
+        assert_eq!(json!(42), encoded["data"]["dp"]["value"]);
+        assert_eq!(json!(1), encoded["sequence"]);
+    }
+
+    #[test]
+    fn encode_viss_v2_get_metadata() {
+        let response = Response::from(ActionSuccessResponse::GetMetadata {
+            request_id: ReqID::ReqIDInt(6),
+            metadata: json!({}),
+            timestamp: 0,
+        });
+        assert_eq!(
+            json!({ "action": "get", "requestId": "6", "metadata": {}, "ts": "1970-01-01T00:00:00.000Z" }),
+            encode_viss_v2(response)
+        );
+    }
+
+    #[test]
+    fn encode_viss_v2_error() {
+        let outbound = Codec::VissV2
+            .encode(new_subscribe_error(ReqID::ReqIDInt(7), StatusCode::NOT_FOUND.into()).into());
+        assert_eq!(StatusCode::NOT_FOUND, outbound.status());
+        let encoded: Value = from_str(&outbound.into_text()).unwrap();
+        assert!(encoded["ts"].is_string());
+        assert_eq!(None, encoded.get("timestamp"));
+        assert_eq!(json!(404), encoded["error"]["number"]);
+    }
+
+    #[test]
+    fn encode_err_when_timestamp_out_of_range() {
+        let response = Response::from(ActionSuccessResponse::Get {
+            request_id: ReqID::ReqIDInt(7),
+            value: json!(42),
+            timestamp: u128::MAX,
+        });
+        let outbound = Codec::VissV2.encode(response);
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, outbound.status());
+    }
+
+    #[test]
+    fn encode_legacy_get() {
+        let response = Response::from(ActionSuccessResponse::Get {
+            request_id: ReqID::ReqIDInt(7),
+            value: json!(42),
+            timestamp: 0,
+        })
+        .with_data(vec![DataPoint {
+            path: "Vehicle.Speed".into(),
+            value: json!(42),
+            timestamp: 0,
+        }]);
+        let encoded: Value = from_str(&Codec::Legacy.encode(response).into_text()).unwrap();
+        assert_eq!(
+            json!({ "action": "get", "requestId": "7", "value": 42, "timestamp": 0 }),
+            encoded
+        );
+    }
+}
+
+/// Websocket subprotocol of the W3C VIS draft
+pub const PROTOCOL_LEGACY: &str = "wvss1.0";
+
+/// Websocket subprotocol of VISS v2
+pub const PROTOCOL_VISS_V2: &str = "VISSv2";
+
+/// Websocket subprotocols supported by the server
+pub(crate) const PROTOCOLS: &[&str] = &[PROTOCOL_VISS_V2, PROTOCOL_LEGACY];
+
+///
+/// Message format of a client session.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Codec {
+    /// W3C VIS draft, e.g. the `getMetadata` action and millisecond timestamps
+    Legacy,
+    /// VISS v2, e.g. `data`/`dp` value envelopes and ISO-8601 timestamps
+    VissV2,
+}
+
+impl Codec {
+    ///
+    /// Codec of the first requested subprotocol the server supports, this is the subprotocol
+    /// the websocket handshake selects. Sessions without a supported subprotocol use the legacy format.
+    ///
+    pub fn negotiate(r: &HttpRequest) -> Self {
+        let protocol = r
+            .headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|protocols| protocols.to_str().ok())
+            .and_then(|protocols| {
+                protocols
+                    .split(',')
+                    .map(str::trim)
+                    .find(|protocol| PROTOCOLS.contains(protocol))
+            });
+
+        match protocol {
+            Some(PROTOCOL_VISS_V2) => Codec::VissV2,
+            _ => Codec::Legacy,
+        }
+    }
+
+    /// Deserialize a client request.
+    pub fn decode(self, text: &str) -> serde_json::Result<Action> {
+        match self {
+            Codec::Legacy => from_str(text),
+            Codec::VissV2 => from_value(viss_v2_request(from_str(text)?)?),
+        }
+    }
+
+    ///
+    /// Serialize a response or notification in the format of the session.
+    /// Responses that cannot be serialized are replaced by an internal server error.
+    ///
+    pub fn encode(self, response: Response) -> Outbound {
+        let text = match self {
+            Codec::Legacy => Ok(serialize_result(&response.result, internal_server_error)),
+            Codec::VissV2 => viss_v2_response(&response).and_then(|v2| to_string(&v2)),
+        };
+        match text {
+            Ok(text) => Outbound::new(response.key(), response.status(), text),
+            Err(e) => {
+                error!("Failed to serialize response {:?}: {}", response.result, e);
+                let text = serialize_result(&Err(internal_server_error()), internal_server_error);
+                Outbound::new(None, StatusCode::INTERNAL_SERVER_ERROR, text)
+            }
+        }
+    }
+}
+
+fn internal_server_error() -> ActionErrorResponse {
+    // TODO replace subscribe error with subscription error
+    new_subscribe_error(ReqID::ReqIDInt(0), StatusCode::INTERNAL_SERVER_ERROR.into())
+}
+
+///
+/// Response or notification for a client session, serialized by the `Codec` of the session.
+///
+#[derive(Debug)]
+pub(crate) struct Response {
+    result: Result<ActionSuccessResponse, ActionErrorResponse>,
+    /// Signal values of a `get` response or subscription notification, for formats that always contain the signal path.
+    data: Vec<DataPoint>,
+}
+
+///
+/// Value of a single signal, a `get` response of a branch or wildcard path contains one for each matching signal.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DataPoint {
+    pub path: ActionPath,
+    pub value: Value,
+    pub timestamp: u128,
+}
+
+impl Response {
+    pub fn with_data(self, data: Vec<DataPoint>) -> Self {
+        Self { data, ..self }
+    }
+
+    /// Subscription and signal of a subscription notification, notifications may be dropped for slow clients.
+    fn key(&self) -> Option<(SubscriptionID, Option<ActionPath>)> {
+        match self.result {
+            Ok(ActionSuccessResponse::Subscription {
+                subscription_id,
+                ref path,
+                ..
+            }) => Some((subscription_id, path.clone())),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of the response, the `number` of the error for error responses.
+    fn status(&self) -> StatusCode {
+        match self.result {
+            Ok(_) => StatusCode::OK,
+            Err(ref error) => StatusCode::from_u16(error.error().number)
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+}
+
+impl From<ActionSuccessResponse> for Response {
+    fn from(response: ActionSuccessResponse) -> Self {
+        Self {
+            result: Ok(response),
+            data: Vec::new(),
+        }
+    }
+}
+
+impl From<ActionErrorResponse> for Response {
+    fn from(response: ActionErrorResponse) -> Self {
+        Self {
+            result: Err(response),
+            data: Vec::new(),
+        }
+    }
+}
+
+///
+/// Translate a VISS v2 request to the legacy format.
+/// The `filter` of `get` requests selects metadata, the `filter` of `subscribe` requests
+/// is translated to `Filters`, only the `timebased`, `change` and `range` filters are supported.
+///
+fn viss_v2_request(mut request: Value) -> serde_json::Result<Value> {
+    if let Value::Object(ref mut object) = request {
+        if let Some(request_id) = object.get_mut("requestId") {
+            if request_id.is_number() {
+                *request_id = Value::String(request_id.to_string());
+            }
+        }
+
+        if let Some(Value::String(path)) = object.get_mut("path") {
+            *path = path.replace('/', ".");
+        }
+
+        let action = object
+            .get("action")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        match (action.as_str(), object.remove("filter")) {
+            (_, None) => {}
+            ("get", Some(filter)) => match filter["type"].as_str() {
+                Some("static-metadata") | Some("metadata") => {
+                    object.insert("action".to_string(), json!("getMetadata"));
+                }
+                other => {
+                    return Err(Error::custom(format!(
+                        "unsupported get filter type {:?}",
+                        other
+                    )))
+                }
+            },
+            ("subscribe", Some(filter)) => {
+                object.insert("filters".to_string(), subscribe_filters(filter)?);
+            }
+            (_, Some(_)) => {
+                return Err(Error::custom(format!(
+                    "filter is not supported for {}",
+                    action
+                )))
+            }
+        }
+    }
+    Ok(request)
+}
+
+fn subscribe_filters(filter: Value) -> serde_json::Result<Value> {
+    let filters = match filter {
+        Value::Array(filters) => filters,
+        filter => vec![filter],
+    };
+
+    let mut legacy = Map::new();
+    for filter in filters {
+        let parameter = &filter["parameter"];
+        match filter["type"].as_str() {
+            Some("timebased") => {
+                legacy.insert("interval".to_string(), number(&parameter["period"])?);
+            }
+            // Only absolute changes are supported
+            Some("change") if parameter["logic-op"].as_str().unwrap_or("ne") == "ne" => {
+                legacy.insert("minChange".to_string(), number(&parameter["diff"])?);
+            }
+            Some("range") => {
+                legacy.insert("range".to_string(), range(parameter)?);
+            }
+            _ => {
+                return Err(Error::custom(format!(
+                    "unsupported subscribe filter {}",
+                    filter
+                )))
+            }
+        }
+    }
+    Ok(Value::Object(legacy))
+}
+
+fn range(parameter: &Value) -> serde_json::Result<Value> {
+    let conditions = match parameter {
+        Value::Array(conditions) => conditions.iter().collect(),
+        condition => vec![condition],
+    };
+
+    // `above` and `below` include the bound, `gt` and `lt` exclude it
+    let mut range = Map::new();
+    for condition in conditions {
+        let (bound, exclusive) = match condition["logic-op"].as_str() {
+            Some("gt") => ("above", true),
+            Some("ge") => ("above", false),
+            Some("lt") => ("below", true),
+            Some("le") => ("below", false),
+            _ => {
+                return Err(Error::custom(format!(
+                    "unsupported range condition {}",
+                    condition
+                )))
+            }
+        };
+        range.insert(bound.to_string(), number(&condition["boundary"])?);
+        range.insert(format!("{}Exclusive", bound), json!(exclusive));
+    }
+    Ok(Value::Object(range))
+}
+
+/// VISS v2 passes numbers as strings
+fn number(value: &Value) -> serde_json::Result<Value> {
+    let number = match value {
+        Value::String(s) => from_str(s)?,
+        value => value.clone(),
+    };
+    if number.is_number() {
+        Ok(number)
+    } else {
+        Err(Error::custom(format!("not a number: {}", value)))
+    }
+}
+
+///
+/// VISS v2 format of a response or notification.
+/// Values are wrapped in a `data` object with the signal `path` and a `dp` data point, an array of `data` objects
+/// for branch and wildcard paths. Millisecond timestamps are replaced by ISO-8601 `ts` timestamps.
+///
+fn viss_v2_response(response: &Response) -> serde_json::Result<Value> {
+    match response.result {
+        Ok(ActionSuccessResponse::Get {
+            request_id,
+            ref value,
+            timestamp,
+        }) => {
+            let ts = iso_timestamp(timestamp)?;
+            Ok(json!({
+                "action": "get",
+                "requestId": request_id,
+                "data": viss_v2_data(&response.data, value, &ts)?,
+                "ts": ts,
+            }))
+        }
+        Ok(ActionSuccessResponse::Subscription {
+            subscription_id,
+            ref value,
+            sequence,
+            timestamp,
+            ..
+        }) => {
+            let ts = iso_timestamp(timestamp)?;
+            let mut notification = json!({
+                "action": "subscription",
+                "subscriptionId": subscription_id,
+                "data": viss_v2_data(&response.data, value, &ts)?,
+                "ts": ts,
+            });
+            if let Some(sequence) = sequence {
+                notification["sequence"] = json!(sequence);
+            }
+            Ok(notification)
+        }
+        Ok(ActionSuccessResponse::GetMetadata {
+            request_id,
+            ref metadata,
+            timestamp,
+        }) => Ok(json!({
+            "action": "get",
+            "requestId": request_id,
+            "metadata": metadata,
+            "ts": iso_timestamp(timestamp)?,
+        })),
+        Ok(ref success) => with_iso_timestamp(to_value(success)?),
+        Err(ref error) => with_iso_timestamp(to_value(error)?),
+    }
+}
+
+/// Only the `value` of the response is known without `DataPoint`s, e.g. for values of unknown signals
+fn viss_v2_data(data: &[DataPoint], value: &Value, ts: &Value) -> serde_json::Result<Value> {
+    let data_point = |data_point: &DataPoint| -> serde_json::Result<Value> {
+        Ok(json!({
+            "path": data_point.path.to_string(),
+            "dp": { "value": data_point.value, "ts": iso_timestamp(data_point.timestamp)? },
+        }))
+    };
+    match data {
+        [] => Ok(json!({ "dp": { "value": value, "ts": ts } })),
+        [single] => data_point(single),
+        data => data
+            .iter()
+            .map(data_point)
+            .collect::<serde_json::Result<_>>()
+            .map(Value::Array),
+    }
+}
+
+fn with_iso_timestamp(mut response: Value) -> serde_json::Result<Value> {
+    if let Value::Object(ref mut object) = response {
+        if let Some(timestamp) = object.remove("timestamp") {
+            let timestamp = timestamp
+                .as_u64()
+                .ok_or_else(|| Error::custom(format!("invalid timestamp {}", timestamp)))?;
+            object.insert("ts".to_string(), iso_timestamp(timestamp.into())?);
+        }
+    }
+    Ok(response)
+}
+
+fn iso_timestamp(timestamp: u128) -> serde_json::Result<Value> {
+    let ms = u64::try_from(timestamp)
+        .map_err(|_| Error::custom(format!("timestamp out of range {}", timestamp)))?;
+    Ok(json!(humantime::format_rfc3339_millis(
+        UNIX_EPOCH + Duration::from_millis(ms)
+    )
+    .to_string()))
+}
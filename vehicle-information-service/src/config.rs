@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! Server configuration, pass a `Config` to `AppState::new`.
+//!
+
+use std::time::Duration;
+
+use crate::outbound::SlowConsumerPolicy;
+
+///
+/// Server configuration
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use vehicle_information_service::{Config, SlowConsumerPolicy};
+///
+/// let config = Config {
+///     set_timeout: Duration::from_secs(1),
+///     slow_consumer_policy: SlowConsumerPolicy::CoalesceLatest,
+///     ..Default::default()
+/// };
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Time a `set` recipient may take to respond, before the client receives a `GATEWAY_TIMEOUT` error.
+    pub set_timeout: Duration,
+    /// Time a `get` provider may take to respond, before the client receives a `GATEWAY_TIMEOUT` error.
+    pub get_timeout: Duration,
+    /// Time after which a signal without updates becomes unavailable, unless a validity is set for its path.
+    /// `None` if signals stay available until they are invalidated.
+    pub signal_validity: Option<Duration>,
+    /// Minimum `interval` filter of subscriptions, shorter intervals requested by clients are raised to this value.
+    pub min_interval: Duration,
+    /// Maximum number of bytes queued per client session, before the `slow_consumer_policy` is applied.
+    pub outbound_budget: usize,
+    /// What to do when a client exceeds its `outbound_budget`.
+    pub slow_consumer_policy: SlowConsumerPolicy,
+    /// Time the subscriptions of a disconnected client session are kept, if the session requested a resume token.
+    pub resume_grace_period: Duration,
+    /// Send the cached value as the first notification of a new subscription,
+    /// unless the client sets `initialValue` in its subscribe request.
+    pub initial_value: bool,
+    /// Maximum number of subscriptions of all clients, `None` for no limit.
+    pub max_subscriptions: Option<usize>,
+    /// Maximum number of subscriptions of a single client session, `None` for no limit.
+    pub max_subscriptions_per_client: Option<usize>,
+    /// Maximum number of subscriptions to the same path, `None` for no limit.
+    /// Subscriptions are counted by their exact subscribed path, a subscription to a branch
+    /// or wildcard path does not count towards the limit of the signal paths it covers.
+    pub max_subscriptions_per_path: Option<usize>,
+    /// Time after which a keep-alive comment is sent to idle Server-Sent Events subscriptions,
+    /// closed connections are only noticed when writing to them. Zero disables keep-alive comments.
+    pub sse_keep_alive: Duration,
+    /// Time the rejected authorize attempts of an HTTP client are kept after its latest rejected attempt.
+    /// Websocket sessions count their attempts for the lifetime of the session.
+    pub authorize_lockout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            set_timeout: Duration::from_secs(5),
+            get_timeout: Duration::from_secs(5),
+            signal_validity: None,
+            min_interval: Duration::from_millis(100),
+            outbound_budget: 1024 * 1024,
+            slow_consumer_policy: SlowConsumerPolicy::DropOldest,
+            resume_grace_period: Duration::from_secs(30),
+            initial_value: false,
+            max_subscriptions: None,
+            max_subscriptions_per_client: None,
+            max_subscriptions_per_path: None,
+            sse_keep_alive: Duration::from_secs(15),
+            authorize_lockout: Duration::from_secs(5 * 60),
+        }
+    }
+}
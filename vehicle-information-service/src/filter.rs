@@ -1,20 +1,25 @@
 // SPDX-License-Identifier: MIT
 
-use crate::api_type::Filters;
-use serde_json::{Number, Value};
+use crate::api_type::{Combinator, Edge, Filters, RangeMode};
+use crate::metadata::MetadataNode;
+use serde_json::{Map, Number, Value};
 use std::cmp::{Ord, Ordering};
-use std::ops::Sub;
-use std::time::{Duration, SystemTime};
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::time::SystemTime;
 
 #[cfg(test)]
 mod tests {
-    use crate::api_type::{FilterRange, Filters};
+    use crate::api_type::{Combinator, Edge, FilterRange, Filters, RangeMode};
     use crate::filter;
     use crate::filter::{
-        interval, is_in_filter_range, is_min_change, value_as_number, SerdeNumber,
+        deep_diff, is_in_filter_range, is_min_change, validate, value_as_number, FilterNumber,
+        FilterState,
     };
-    use serde_json::{json, Value};
-    use std::time::{Duration, SystemTime};
+    use crate::metadata::MetadataNode;
+    use proptest::prelude::*;
+    use serde_json::{json, Number, Value};
+    use std::time::SystemTime;
 
     #[test]
     fn value_as_number_ok_when_int() {
@@ -33,6 +38,7 @@ mod tests {
         let fr = FilterRange {
             below: Some(110.into()),
             above: None,
+            ..Default::default()
         };
         assert_eq!(
             Ok(true),
@@ -42,7 +48,9 @@ mod tests {
                     interval: None,
                     range: Some(fr),
                     min_change: None,
+                    ..Default::default()
                 },
+                &mut FilterState::default(),
             )
         );
     }
@@ -52,6 +60,7 @@ mod tests {
         let fr = FilterRange {
             below: Some(10.into()),
             above: None,
+            ..Default::default()
         };
         assert_eq!(
             Ok(false),
@@ -61,7 +70,9 @@ mod tests {
                     interval: None,
                     range: Some(fr),
                     min_change: None,
+                    ..Default::default()
                 },
+                &mut FilterState::default(),
             )
         );
     }
@@ -71,6 +82,7 @@ mod tests {
         let fr = FilterRange {
             below: None,
             above: Some(100.into()),
+            ..Default::default()
         };
         assert_eq!(
             Ok(true),
@@ -80,7 +92,9 @@ mod tests {
                     interval: None,
                     range: Some(fr),
                     min_change: None,
+                    ..Default::default()
                 },
+                &mut FilterState::default(),
             )
         );
     }
@@ -90,6 +104,7 @@ mod tests {
         let fr = FilterRange {
             below: None,
             above: Some(110.into()),
+            ..Default::default()
         };
         assert_eq!(
             Ok(false),
@@ -99,7 +114,9 @@ mod tests {
                     interval: None,
                     range: Some(fr),
                     min_change: None,
+                    ..Default::default()
                 },
+                &mut FilterState::default(),
             )
         );
     }
@@ -109,6 +126,7 @@ mod tests {
         let fr = FilterRange {
             below: None,
             above: None,
+            ..Default::default()
         };
         assert_eq!(
             Ok(true),
@@ -118,7 +136,9 @@ mod tests {
                     interval: None,
                     range: Some(fr),
                     min_change: None,
+                    ..Default::default()
                 },
+                &mut FilterState::default(),
             )
         );
     }
@@ -128,6 +148,7 @@ mod tests {
         let fr = FilterRange {
             below: Some(120.into()),
             above: Some(100.into()),
+            ..Default::default()
         };
         assert_eq!(
             Ok(true),
@@ -137,7 +158,9 @@ mod tests {
                     interval: None,
                     range: Some(fr),
                     min_change: None,
+                    ..Default::default()
                 },
+                &mut FilterState::default(),
             )
         );
     }
@@ -147,6 +170,7 @@ mod tests {
         let fr = FilterRange {
             below: Some(120.into()),
             above: Some(100.into()),
+            ..Default::default()
         };
         assert_eq!(
             Ok(true),
@@ -156,7 +180,9 @@ mod tests {
                     interval: None,
                     range: Some(fr),
                     min_change: None,
+                    ..Default::default()
                 },
+                &mut FilterState::default(),
             )
         );
     }
@@ -166,6 +192,7 @@ mod tests {
         let fr = FilterRange {
             below: Some(120.into()),
             above: Some(115.into()),
+            ..Default::default()
         };
         assert_eq!(
             Ok(false),
@@ -175,7 +202,9 @@ mod tests {
                     interval: None,
                     range: Some(fr),
                     min_change: None,
+                    ..Default::default()
                 },
+                &mut FilterState::default(),
             )
         );
     }
@@ -186,6 +215,7 @@ mod tests {
             interval: None,
             range: None,
             min_change: Some(5.into()),
+            ..Default::default()
         };
         assert_eq!(
             Ok(true),
@@ -211,6 +241,7 @@ mod tests {
             interval: None,
             range: None,
             min_change: Some(5.into()),
+            ..Default::default()
         };
         assert_eq!(
             Ok(false),
@@ -228,6 +259,7 @@ mod tests {
             interval: None,
             range: None,
             min_change: None,
+            ..Default::default()
         });
         assert_eq!(
             Ok(true),
@@ -235,6 +267,7 @@ mod tests {
                 &Value::Number(00.into()),
                 &Some((SystemTime::now(), Value::Number(101.into()))),
                 &f,
+                &mut FilterState::default(),
             )
         );
     }
@@ -245,6 +278,7 @@ mod tests {
             interval: None,
             range: None,
             min_change: None,
+            ..Default::default()
         });
         assert_eq!(
             Ok(false),
@@ -252,6 +286,7 @@ mod tests {
                 &Value::Number(101.into()),
                 &Some((SystemTime::now(), Value::Number(101.into()))),
                 &f,
+                &mut FilterState::default(),
             )
         );
     }
@@ -262,6 +297,7 @@ mod tests {
             interval: None,
             range: None,
             min_change: Some(5.into()),
+            ..Default::default()
         });
         assert_eq!(
             Ok(false),
@@ -269,6 +305,7 @@ mod tests {
                 &Value::Number(101.into()),
                 &Some((SystemTime::now(), Value::Number(104.into()))),
                 &f,
+                &mut FilterState::default(),
             )
         );
     }
@@ -279,6 +316,7 @@ mod tests {
             interval: None,
             range: None,
             min_change: Some(5.into()),
+            ..Default::default()
         });
         assert_eq!(
             Ok(true),
@@ -286,6 +324,7 @@ mod tests {
                 &Value::Number(100.into()),
                 &Some((SystemTime::now(), Value::Number(106.into()))),
                 &f,
+                &mut FilterState::default(),
             )
         );
     }
@@ -296,10 +335,16 @@ mod tests {
             interval: None,
             range: None,
             min_change: Some(5.into()),
+            ..Default::default()
         });
         assert_eq!(
             Ok(true),
-            filter::matches(&json!(100.1), &Some((SystemTime::now(), json!(106.7))), &f,)
+            filter::matches(
+                &json!(100.1),
+                &Some((SystemTime::now(), json!(106.7))),
+                &f,
+                &mut FilterState::default()
+            )
         );
     }
 
@@ -309,10 +354,16 @@ mod tests {
             interval: None,
             range: None,
             min_change: Some(5.into()),
+            ..Default::default()
         });
         assert_eq!(
             Ok(false),
-            filter::matches(&json!(100.1), &Some((SystemTime::now(), json!(100.5))), &f,)
+            filter::matches(
+                &json!(100.1),
+                &Some((SystemTime::now(), json!(100.5))),
+                &f,
+                &mut FilterState::default()
+            )
         );
     }
 
@@ -322,6 +373,7 @@ mod tests {
             interval: None,
             range: None,
             min_change: None,
+            ..Default::default()
         });
         assert_eq!(
             Ok(true),
@@ -329,6 +381,7 @@ mod tests {
                 &Value::String("a".to_owned()),
                 &Some((SystemTime::now(), Value::String("b".to_owned()))),
                 &f,
+                &mut FilterState::default(),
             )
         );
     }
@@ -339,6 +392,7 @@ mod tests {
             interval: None,
             range: None,
             min_change: None,
+            ..Default::default()
         });
         assert_eq!(
             Ok(false),
@@ -346,6 +400,7 @@ mod tests {
                 &Value::String("a".to_owned()),
                 &Some((SystemTime::now(), Value::String("a".to_owned()))),
                 &f,
+                &mut FilterState::default(),
             )
         );
     }
@@ -356,7 +411,12 @@ mod tests {
         let filter = None;
         assert_eq!(
             Ok(false),
-            filter::matches(&Value::Number(100.into()), last_val, &filter,)
+            filter::matches(
+                &Value::Number(100.into()),
+                last_val,
+                &filter,
+                &mut FilterState::default()
+            )
         );
     }
 
@@ -366,53 +426,660 @@ mod tests {
         let filter = None;
         assert_eq!(
             Ok(true),
-            filter::matches(&Value::Number(101.into()), last_val, &filter,)
+            filter::matches(
+                &Value::Number(101.into()),
+                last_val,
+                &filter,
+                &mut FilterState::default()
+            )
+        );
+    }
+
+    #[test]
+    fn matches_one_of() {
+        let f = Some(Filters {
+            one_of: Some(vec![json!("PARK"), json!("DRIVE")]),
+            ..Default::default()
+        });
+        assert_eq!(
+            Ok(true),
+            filter::matches(&json!("DRIVE"), &None, &f, &mut FilterState::default())
+        );
+        assert_eq!(
+            Ok(false),
+            filter::matches(&json!("REVERSE"), &None, &f, &mut FilterState::default())
+        );
+    }
+
+    #[test]
+    fn matches_edge() {
+        let rising = Some(Filters {
+            edge: Some(Edge::Rising),
+            ..Default::default()
+        });
+        let last_false = Some((SystemTime::now(), json!(false)));
+        let last_true = Some((SystemTime::now(), json!(true)));
+        assert_eq!(
+            Ok(true),
+            filter::matches(
+                &json!(true),
+                &last_false,
+                &rising,
+                &mut FilterState::default()
+            )
+        );
+        assert_eq!(
+            Ok(false),
+            filter::matches(
+                &json!(false),
+                &last_true,
+                &rising,
+                &mut FilterState::default()
+            )
+        );
+        assert_eq!(
+            Ok(false),
+            filter::matches(&json!(true), &None, &rising, &mut FilterState::default())
+        );
+        assert_eq!(
+            Err(filter::Error::ValueIsNotABool),
+            filter::matches(&json!(1), &last_false, &rising, &mut FilterState::default())
+        );
+
+        let falling = Some(Filters {
+            edge: Some(Edge::Falling),
+            ..Default::default()
+        });
+        assert_eq!(
+            Ok(true),
+            filter::matches(
+                &json!(false),
+                &last_true,
+                &falling,
+                &mut FilterState::default()
+            )
         );
     }
 
     #[test]
-    fn interval_true_when_duration_to_last_val_greater_than_interval() {
+    fn is_in_filter_range_outside() {
         let f = Filters {
-            interval: Some(100),
-            range: None,
-            min_change: None,
+            range: Some(FilterRange {
+                below: Some(90.into()),
+                above: Some(10.into()),
+                mode: RangeMode::Outside,
+                ..Default::default()
+            }),
+            ..Default::default()
         };
-        let now = SystemTime::now();
-        let later = now.clone() + Duration::from_secs(10);
-        assert!(interval(
-            later,
-            &Some((now, Value::String("a".to_owned()))),
-            &f,
-        ));
+        let mut state = FilterState::default();
+        assert_eq!(Ok(false), is_in_filter_range(&json!(50), &f, &mut state));
+        assert_eq!(Ok(true), is_in_filter_range(&json!(95), &f, &mut state));
+        // Still outside, only the crossing is notified
+        assert_eq!(Ok(false), is_in_filter_range(&json!(99), &f, &mut state));
+        assert_eq!(Ok(false), is_in_filter_range(&json!(-5), &f, &mut state));
+        assert_eq!(Ok(false), is_in_filter_range(&json!(50), &f, &mut state));
+        assert_eq!(Ok(true), is_in_filter_range(&json!(-5), &f, &mut state));
     }
 
     #[test]
-    fn interval_false_when_duration_to_last_val_smaller_than_interval() {
+    fn is_in_filter_range_first_value_on_matching_side() {
         let f = Filters {
-            interval: Some(1000000),
-            range: None,
-            min_change: None,
+            range: Some(FilterRange {
+                above: Some(100.into()),
+                ..Default::default()
+            }),
+            ..Default::default()
         };
-        let now = SystemTime::now();
-        let later = now.clone() + Duration::from_millis(10);
-        assert!(!interval(later, &Some((now, Value::Null)), &f));
+        let mut state = FilterState::default();
+        assert_eq!(Ok(true), is_in_filter_range(&json!(101), &f, &mut state));
+        assert_eq!(Ok(false), is_in_filter_range(&json!(102), &f, &mut state));
     }
 
     #[test]
-    fn serde_number_eq() {
-        assert_eq!(SerdeNumber(1.into()), SerdeNumber(1.into()));
-        assert!(SerdeNumber(1.into()) != SerdeNumber(100.into()));
+    fn is_in_filter_range_exclusive_bounds() {
+        let range = |below_exclusive: bool, above_exclusive: bool| Filters {
+            range: Some(FilterRange {
+                below: Some(20.into()),
+                above: Some(10.into()),
+                below_exclusive,
+                above_exclusive,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        for (val, inclusive, exclusive) in &[(10, true, false), (15, true, true), (20, true, false)]
+        {
+            assert_eq!(
+                Ok(*inclusive),
+                is_in_filter_range(
+                    &json!(val),
+                    &range(false, false),
+                    &mut FilterState::default()
+                )
+            );
+            assert_eq!(
+                Ok(*exclusive),
+                is_in_filter_range(&json!(val), &range(true, true), &mut FilterState::default())
+            );
+        }
+    }
+
+    /// Notifications of `values` passed through the range filter one after another.
+    fn range_notifications(f: &Filters, values: &[f64]) -> Vec<bool> {
+        let mut state = FilterState::default();
+        values
+            .iter()
+            .map(|val| is_in_filter_range(&json!(val), f, &mut state).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn is_in_filter_range_hysteresis_inside_oscillation() {
+        let f = Filters {
+            range: Some(FilterRange {
+                below: Some(90.into()),
+                hysteresis: Some(5.into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![false, true, false, false, false, false, true],
+            // Enters at the bound, oscillating below 95 does not re-arm, 96 re-arms
+            range_notifications(&f, &[92.0, 90.0, 94.0, 89.0, 95.0, 96.0, 90.0])
+        );
+    }
+
+    #[test]
+    fn is_in_filter_range_hysteresis_outside_oscillation() {
+        let f = Filters {
+            range: Some(FilterRange {
+                below: Some(110.into()),
+                mode: RangeMode::Outside,
+                hysteresis: Some(5.into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![false, true, false, false, false, false, true],
+            // Fires above 110, oscillating above 105 does not re-arm, 104 re-arms
+            range_notifications(&f, &[108.0, 110.5, 109.0, 111.0, 105.0, 104.0, 111.0])
+        );
+    }
+
+    #[test]
+    fn seed_range_hysteresis() {
+        let f = Some(Filters {
+            range: Some(FilterRange {
+                below: Some(90.into()),
+                above: None,
+                hysteresis: Some(5.into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let mut state = FilterState::default();
+        filter::seed(&json!(80), &f, &mut state);
+        let last_value = Some((SystemTime::now(), json!(80)));
+        // The seeded value already matched
+        assert_eq!(
+            Ok(false),
+            filter::matches(&json!(89), &last_value, &f, &mut state)
+        );
+        assert_eq!(
+            Ok(false),
+            filter::matches(&json!(96), &last_value, &f, &mut state)
+        );
+        assert_eq!(
+            Ok(true),
+            filter::matches(&json!(89), &last_value, &f, &mut state)
+        );
+    }
+
+    #[test]
+    fn matches_combine_any() {
+        let f = Some(Filters {
+            range: Some(FilterRange {
+                below: None,
+                above: Some(100.into()),
+                ..Default::default()
+            }),
+            min_change: Some(10.into()),
+            combine: Combinator::Any,
+            ..Default::default()
+        });
+        let last_value = Some((SystemTime::now(), json!(50)));
+        let mut state = FilterState::default();
+        assert_eq!(
+            Ok(true),
+            filter::matches(&json!(101), &last_value, &f, &mut state)
+        );
+        assert_eq!(
+            Ok(true),
+            filter::matches(&json!(65), &last_value, &f, &mut state)
+        );
+        assert_eq!(
+            Ok(false),
+            filter::matches(&json!(55), &last_value, &f, &mut state)
+        );
+
+        let no_conditions = Some(Filters {
+            combine: Combinator::Any,
+            ..Default::default()
+        });
+        assert_eq!(
+            Ok(true),
+            filter::matches(&json!(55), &last_value, &no_conditions, &mut state)
+        );
+    }
+
+    #[test]
+    fn deep_diff_merge_patch() {
+        assert_eq!(
+            json!({ "b": { "c": 3 }, "d": null, "e": 5 }),
+            deep_diff(
+                &json!({ "a": 1, "b": { "c": 2, "f": 6 }, "d": 4 }),
+                &json!({ "a": 1, "b": { "c": 3, "f": 6 }, "e": 5 })
+            )
+        );
+        assert_eq!(json!(2), deep_diff(&json!({ "a": 1 }), &json!(2)));
+    }
+
+    #[test]
+    fn validate_filters() {
+        let node: MetadataNode = serde_json::from_value(json!({
+            "type": "sensor",
+            "datatype": "boolean"
+        }))
+        .unwrap();
+
+        let range = Filters {
+            range: Some(FilterRange {
+                below: Some(10.into()),
+                above: None,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(validate(&range, None).is_ok());
+        assert!(validate(&range, Some(&node)).is_err());
+
+        let edge = Filters {
+            edge: Some(Edge::Rising),
+            ..Default::default()
+        };
+        assert!(validate(&edge, Some(&node)).is_ok());
+
+        let one_of = Filters {
+            one_of: Some(vec![json!("a")]),
+            ..Default::default()
+        };
+        assert!(validate(&one_of, Some(&node)).is_err());
+        assert!(validate(
+            &Filters {
+                one_of: Some(vec![]),
+                ..Default::default()
+            },
+            None
+        )
+        .is_err());
+
+        let hysteresis = |below: Option<i64>, hysteresis: i64| Filters {
+            range: Some(FilterRange {
+                below: below.map(Into::into),
+                above: None,
+                hysteresis: Some(hysteresis.into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(validate(&hysteresis(Some(10), 2), None).is_ok());
+        assert!(validate(&hysteresis(Some(10), -2), None).is_err());
+        assert!(validate(&hysteresis(None, 2), None).is_err());
+    }
+
+    #[test]
+    fn validate_coalesce() {
+        let coalesce = |coalesce: u64, interval: Option<u64>| Filters {
+            coalesce: Some(coalesce),
+            interval,
+            ..Default::default()
+        };
+        assert!(validate(&coalesce(100, None), None).is_ok());
+        assert!(validate(&coalesce(0, None), None).is_err());
+        assert!(validate(&coalesce(100, Some(100)), None).is_err());
+    }
+
+    #[test]
+    fn validate_interval() {
+        let interval = |interval: u64| Filters {
+            interval: Some(interval),
+            ..Default::default()
+        };
+        assert!(validate(&interval(100), None).is_ok());
+        assert!(validate(&interval(0), None).is_err());
+    }
+
+    fn number(num: impl Into<Number>) -> FilterNumber {
+        FilterNumber::from(&num.into())
+    }
+
+    fn float(f: f64) -> FilterNumber {
+        FilterNumber::from(&Number::from_f64(f).unwrap())
+    }
+
+    ///
+    /// JSON numbers of every kind, with values for which f64 arithmetic is exact,
+    /// so that f64 can be used as reference.
+    ///
+    fn json_number() -> impl Strategy<Value = Value> {
+        prop_oneof![
+            (0u64..1 << 40).prop_map(|u| json!(u)),
+            (-(1i64 << 40)..0).prop_map(|i| json!(i)),
+            (-(1i64 << 40)..1 << 40).prop_map(|f| json!(f as f64 / 1024.0)),
+        ]
+    }
+
+    fn as_f64(val: &Value) -> f64 {
+        val.as_f64().unwrap()
+    }
+
+    ///
+    /// Any `u64` or `i64`, including values beyond `i64::MAX` and values that f64 can not represent.
+    ///
+    fn json_int() -> impl Strategy<Value = Value> {
+        prop_oneof![
+            any::<u64>().prop_map(|u| json!(u)),
+            any::<i64>().prop_map(|i| json!(i)),
+        ]
+    }
+
+    ///
+    /// The value multiplied by 1024 as integer, exact for all integers and for floats
+    /// that are multiples of 1/1024, e.g. the floats of `json_number`.
+    ///
+    fn exact(val: &Value) -> i128 {
+        if let Some(u) = val.as_u64() {
+            i128::from(u) * 1024
+        } else if let Some(i) = val.as_i64() {
+            i128::from(i) * 1024
+        } else {
+            let scaled = as_f64(val) * 1024.0;
+            assert_eq!(0.0, scaled.fract(), "{} is not exact", val);
+            scaled as i128
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_is_min_change(val in json_number(), last in json_number(), min_change in json_number()) {
+            let f = Filters {
+                min_change: Some(min_change.as_f64().map(f64::abs).and_then(Number::from_f64).unwrap()),
+                ..Default::default()
+            };
+            let expected = (as_f64(&last) - as_f64(&val)).abs() >= as_f64(&min_change).abs();
+            prop_assert_eq!(
+                Ok(expected),
+                is_min_change(&val, &Some((SystemTime::now(), last.clone())), &f)
+            );
+            // The delta is symmetric
+            prop_assert_eq!(
+                Ok(expected),
+                is_min_change(&last, &Some((SystemTime::now(), val)), &f)
+            );
+        }
+
+        #[test]
+        fn prop_is_in_filter_range(val in json_number(), below in json_number(), above in json_number()) {
+            let range = |below: Option<&Value>, above: Option<&Value>| Filters {
+                range: Some(FilterRange {
+                    below: below.and_then(Value::as_number).cloned(),
+                    above: above.and_then(Value::as_number).cloned(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            prop_assert_eq!(
+                Ok(as_f64(&val) <= as_f64(&below)),
+                is_in_filter_range(&val, &range(Some(&below), None), &mut FilterState::default())
+            );
+            prop_assert_eq!(
+                Ok(as_f64(&val) >= as_f64(&above)),
+                is_in_filter_range(&val, &range(None, Some(&above)), &mut FilterState::default())
+            );
+            prop_assert_eq!(
+                Ok(as_f64(&val) <= as_f64(&below) && as_f64(&val) >= as_f64(&above)),
+                is_in_filter_range(&val, &range(Some(&below), Some(&above)), &mut FilterState::default())
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_is_min_change_full_int_range(val in json_int(), last in json_int(), min_change in prop_oneof![json_int(), json_number()]) {
+            let min_change = min_change.as_f64().map(f64::abs).and_then(Number::from_f64).unwrap();
+            let expected = (exact(&last) - exact(&val)).abs() >= exact(&json!(min_change));
+            let f = Filters {
+                min_change: Some(min_change),
+                ..Default::default()
+            };
+            prop_assert_eq!(
+                Ok(expected),
+                is_min_change(&val, &Some((SystemTime::now(), last.clone())), &f)
+            );
+            prop_assert_eq!(
+                Ok(expected),
+                is_min_change(&last, &Some((SystemTime::now(), val)), &f)
+            );
+        }
+
+        #[test]
+        fn prop_is_in_filter_range_full_int_range(
+            val in prop_oneof![json_int(), json_number()],
+            below in prop_oneof![json_int(), json_number()],
+            above in prop_oneof![json_int(), json_number()],
+        ) {
+            let f = Filters {
+                range: Some(FilterRange {
+                    below: below.as_number().cloned(),
+                    above: above.as_number().cloned(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            prop_assert_eq!(
+                Ok(exact(&val) <= exact(&below) && exact(&val) >= exact(&above)),
+                is_in_filter_range(&val, &f, &mut FilterState::default())
+            );
+        }
+    }
+
+    #[test]
+    fn filter_number_boundaries() {
+        // 2^53 + 1 is the first integer f64 can not represent, as f64 it rounds to 2^53
+        let two_pow_53 = 9_007_199_254_740_992u64;
+        assert!(number(two_pow_53 + 1) > float(two_pow_53 as f64));
+        assert!(number(two_pow_53 - 1) < float(two_pow_53 as f64));
+        assert_eq!(number(two_pow_53 - 1), float((two_pow_53 - 1) as f64));
+        assert!(number(two_pow_53 + 1) > float((two_pow_53 + 1) as f64));
+        assert!(number(-(two_pow_53 as i64) - 1) < float(-(two_pow_53 as f64)));
+
+        assert_eq!(number(i64::MIN), float(i64::MIN as f64));
+        assert_eq!(FilterNumber::Int(1 << 63), number(i64::MIN).abs());
+        assert!(number(u64::MAX) > number(i64::MAX as u64 + 1));
+        assert_eq!(
+            FilterNumber::Int(i128::from(u64::MAX) - i128::from(i64::MIN)),
+            number(u64::MAX) - number(i64::MIN)
+        );
+        assert_eq!(
+            FilterNumber::Int(i128::from(i64::MIN) - i128::from(u64::MAX)),
+            number(i64::MIN) - number(u64::MAX)
+        );
+
+        // Deltas of 1 between neighbouring large integers are not lost
+        let f = Filters {
+            min_change: Some(1.into()),
+            ..Default::default()
+        };
+        let last = Some((SystemTime::now(), json!(u64::MAX - 1)));
+        assert_eq!(Ok(true), is_min_change(&json!(u64::MAX), &last, &f));
+        let last = Some((SystemTime::now(), json!(two_pow_53)));
+        assert_eq!(Ok(true), is_min_change(&json!(two_pow_53 + 1), &last, &f));
+        assert_eq!(Ok(false), is_min_change(&json!(two_pow_53), &last, &f));
+    }
+
+    #[test]
+    fn filter_number_eq() {
+        assert_eq!(number(1), number(1));
+        assert!(number(1) != number(100));
         let u: i64 = -100;
-        assert!(SerdeNumber(u.into()).abs() == SerdeNumber(100.into()));
-        assert!((SerdeNumber(u.into()) - SerdeNumber(u.into())).abs() == SerdeNumber(0.into()));
-        assert!(SerdeNumber(u.into()).abs() >= SerdeNumber(100.into()));
-        assert!(SerdeNumber(u.into()).abs() > SerdeNumber(50.into()));
+        assert!(number(u).abs() == number(100));
+        assert!((number(u) - number(u)).abs() == number(0));
+        assert!(number(u).abs() >= number(100));
+        assert!(number(u).abs() > number(50));
+    }
+
+    #[test]
+    fn filter_number_cross_type() {
+        assert_eq!(number(1u64), number(1i64));
+        assert_eq!(number(1), float(1.0));
+        assert!(number(-1) < number(0u64));
+        assert!(number(u64::MAX) > number(i64::MIN));
+        // u64::MAX rounds to 2^64 as f64
+        assert!(number(u64::MAX) < float(18_446_744_073_709_551_616.0));
+        assert!(number(i64::MAX) < float(9_223_372_036_854_775_808.0));
+        assert!(number(2) > float(1.5));
+        assert!(number(-2) < float(-1.5));
+        assert!(float(-1.5) > number(-2));
+    }
+
+    #[test]
+    fn filter_number_signed_delta() {
+        assert_eq!(number(15), (number(50u64) - number(65u64)).abs());
+        assert_eq!(
+            float(18_446_744_073_709_551_616.0),
+            number(u64::MAX) + number(1)
+        );
+        assert_eq!(
+            FilterNumber::Int(i128::from(u64::MAX) + 1),
+            number(u64::MAX) - number(-1)
+        );
+        assert_eq!(float(2.5), (float(-1.0) - float(1.5)).abs());
+        assert_eq!(float(0.5), (number(-1) - float(-1.5)).abs());
     }
 }
 
 #[derive(Eq, PartialEq, Debug)]
 pub enum Error {
     ValueIsNotANumber,
+    ValueIsNotABool,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ValueIsNotANumber => write!(f, "Value is not a number"),
+            Error::ValueIsNotABool => write!(f, "Value is not a boolean"),
+        }
+    }
+}
+
+const NUMERIC_DATATYPES: &[&str] = &[
+    "float", "double", "uint8", "uint16", "uint32", "uint64", "int8", "int16", "int32", "int64",
+];
+
+///
+/// Validate the filters of a subscription, before the subscription is created.
+/// If metadata of the subscribed signal is available, the filters are checked against its datatype.
+///
+pub fn validate(filters: &Filters, metadata: Option<&MetadataNode>) -> Result<(), String> {
+    let numeric = filters.range.is_some() || filters.min_change.is_some();
+    if numeric && filters.edge.is_some() {
+        return Err("`edge` can not be combined with `range` or `minChange`".to_string());
+    }
+
+    if let Some(ref min_change) = filters.min_change {
+        if min_change.as_f64().map(|m| m < 0.0).unwrap_or(true) {
+            return Err(format!("`minChange` {} must not be negative", min_change));
+        }
+    }
+
+    if let Some(ref range) = filters.range {
+        if let Some(ref hysteresis) = range.hysteresis {
+            if hysteresis.as_f64().map(|h| h < 0.0).unwrap_or(true) {
+                return Err(format!("`hysteresis` {} must not be negative", hysteresis));
+            }
+            if range.below.is_none() && range.above.is_none() {
+                return Err("`hysteresis` requires a `below` or `above` bound".to_string());
+            }
+        }
+    }
+
+    if filters.interval == Some(0) {
+        return Err("`interval` must be greater than 0".to_string());
+    }
+
+    if let Some(coalesce) = filters.coalesce {
+        if coalesce == 0 {
+            return Err("`coalesce` must be greater than 0".to_string());
+        }
+        if filters.interval.is_some() {
+            return Err("`coalesce` can not be combined with `interval`".to_string());
+        }
+    }
+
+    if let Some(ref one_of) = filters.one_of {
+        if one_of.is_empty() {
+            return Err("`oneOf` requires at least one value".to_string());
+        }
+    }
+
+    let (node, datatype) = match metadata.and_then(|node| Some((node, node.datatype.as_deref()?))) {
+        Some(node_datatype) => node_datatype,
+        // The datatype is unknown, values are checked when notifying
+        None => return Ok(()),
+    };
+
+    if numeric && !NUMERIC_DATATYPES.contains(&datatype) {
+        return Err(format!(
+            "`range` and `minChange` require a numeric signal, datatype is `{}`",
+            datatype
+        ));
+    }
+
+    if filters.edge.is_some() && datatype != "boolean" {
+        return Err(format!(
+            "`edge` requires a boolean signal, datatype is `{}`",
+            datatype
+        ));
+    }
+
+    if filters.deep_diff.unwrap_or(false)
+        && (datatype == "boolean" || datatype == "string" || NUMERIC_DATATYPES.contains(&datatype))
+    {
+        return Err(format!(
+            "`deepDiff` requires an object signal, datatype is `{}`",
+            datatype
+        ));
+    }
+
+    for value in filters.one_of.iter().flatten() {
+        node.validate(value)
+            .map_err(|message| format!("Invalid `oneOf` value: {}", message))?;
+    }
+
+    Ok(())
+}
+
+///
+/// Filter state of a single signal of a subscription, kept between notifications.
+///
+#[derive(Clone, Debug, Default)]
+pub struct FilterState {
+    /// Whether the last value was inside the range, `None` before the first value
+    inside_range: Option<bool>,
 }
 
 ///
@@ -420,32 +1087,46 @@ pub enum Error {
 /// Returns:
 /// Ok(true) : E.g. value changed sufficiently or there was no filter set
 /// Ok(false) : Did not reach change threshold
-/// Err(...): Occurs when the value is not an integer, filters only work for ints
+/// Err(...): Occurs when the value is not of the type the filter requires e.g. a number for `range`
 ///
 pub fn matches(
     val: &Value,
     last_value: &Option<(SystemTime, Value)>,
     filters_opt: &Option<Filters>,
+    state: &mut FilterState,
 ) -> Result<bool, Error> {
     debug!(
         "Matches filter val {:?}, last value {:?}, filters {:?}",
         val, last_value, filters_opt
     );
 
-    let changed_exp = last_value.as_ref().map_or(true, |v| val != &v.1);
+    let changed_exp = last_value.as_ref().map(|v| val != &v.1).unwrap_or(true);
 
     let filters_exp = if let Some(filters) = filters_opt {
-        let interval_exp = interval(SystemTime::now(), last_value, filters);
-
-        let range_exp = is_in_filter_range(&val, filters)?;
-        let min_change_exp = is_min_change(&val, last_value, filters)?;
+        let range_exp = is_in_filter_range(val, filters, state)?;
+        let min_change_exp = is_min_change(val, last_value, filters)?;
+        let one_of_exp = is_one_of(val, filters);
+        let edge_exp = is_edge(val, last_value, filters)?;
         debug!(
-            "Matches filter val {:?}, last value {:?}, filters {:?}, changed_exp? {}, range_exp? {}, min_change_exp? {}",
-            val, last_value, filters, changed_exp, range_exp, min_change_exp,
+            "Matches filter val {:?}, last value {:?}, filters {:?}, changed_exp? {}, range_exp? {}, min_change_exp? {}, one_of_exp? {}, edge_exp? {}",
+            val, last_value, filters, changed_exp, range_exp, min_change_exp, one_of_exp, edge_exp,
         );
 
-        let range_min_exp = range_exp && min_change_exp;
-        interval_exp && range_min_exp
+        let conditions_exp = match filters.combine {
+            Combinator::All => range_exp && min_change_exp && one_of_exp && edge_exp,
+            Combinator::Any => {
+                let conditions = [
+                    (filters.range.is_some(), range_exp),
+                    (filters.min_change.is_some(), min_change_exp),
+                    (filters.one_of.is_some(), one_of_exp),
+                    (filters.edge.is_some(), edge_exp),
+                ];
+                // Without any value condition every value matches
+                conditions.iter().all(|(is_set, _)| !is_set)
+                    || conditions.iter().any(|(is_set, exp)| *is_set && *exp)
+            }
+        };
+        conditions_exp
     } else {
         // filter exp is None
         true
@@ -455,45 +1136,82 @@ pub fn matches(
 }
 
 ///
-/// Extract a Number from a JSON Value or return Error if not possible.
+/// Initialize the filter state from a value that is sent to the client regardless of the filters.
 ///
-fn value_as_number(val: &Value) -> Result<SerdeNumber, Error> {
+pub fn seed(val: &Value, filters_opt: &Option<Filters>, state: &mut FilterState) {
+    if let Some(filters) = filters_opt {
+        // Values that are not numbers are reported once they are filtered
+        let _ = is_in_filter_range(val, filters, state);
+    }
+}
+
+fn value_as_number(val: &Value) -> Result<FilterNumber, Error> {
     if let Value::Number(ref num) = *val {
-        Ok(SerdeNumber(num.clone()))
+        Ok(FilterNumber::from(num))
     } else {
         Err(Error::ValueIsNotANumber)
     }
 }
 
-fn interval(now: SystemTime, last_value: &Option<(SystemTime, Value)>, filters: &Filters) -> bool {
-    last_value.as_ref().map_or(true, |v| {
-        now.duration_since(v.0)
-            .ok()
-            .as_ref()
-            .and_then(|d| filters.interval.map(|i| Duration::from_millis(i) <= *d))
-            .unwrap_or(true)
-    })
-}
-
 ///
-/// Below or above filter
+/// Below or above filter, true when the value crosses into the matching side of the range.
+/// The bounds are fixed, once the range matched the hysteresis delays re-arming until the
+/// value is back across a bound by more than the hysteresis.
 ///
-fn is_in_filter_range(val: &Value, filters: &Filters) -> Result<bool, Error> {
+fn is_in_filter_range(
+    val: &Value,
+    filters: &Filters,
+    state: &mut FilterState,
+) -> Result<bool, Error> {
     if let Some(ref range) = filters.range {
         let num = value_as_number(val)?;
-        let below = range.clone().below.map_or(true, |b| num <= SerdeNumber(b));
-        let above = range.clone().above.map_or(true, |a| num >= SerdeNumber(a));
-        Ok(below && above)
+        let inside_mode = range.mode == RangeMode::Inside;
+        let matched = state.inside_range.map(|inside| inside == inside_mode);
+        // Move the bounds away from the value while it is on the matching side
+        let shift = match range.hysteresis {
+            Some(ref hysteresis) if matched == Some(true) => {
+                let hysteresis = FilterNumber::from(hysteresis);
+                if inside_mode {
+                    hysteresis
+                } else {
+                    FilterNumber::Int(0) - hysteresis
+                }
+            }
+            _ => FilterNumber::Int(0),
+        };
+        let below = range
+            .below
+            .as_ref()
+            .map(|b| {
+                let b = FilterNumber::from(b) + shift;
+                if range.below_exclusive {
+                    num < b
+                } else {
+                    num <= b
+                }
+            })
+            .unwrap_or(true);
+        let above = range
+            .above
+            .as_ref()
+            .map(|a| {
+                let a = FilterNumber::from(a) - shift;
+                if range.above_exclusive {
+                    num > a
+                } else {
+                    num >= a
+                }
+            })
+            .unwrap_or(true);
+        let inside = below && above;
+        state.inside_range = Some(inside);
+        Ok(inside == inside_mode && matched != Some(true))
     } else {
         // No range filter
         Ok(true)
     }
 }
 
-///
-/// Changed by at least x compared to last value.
-/// Returns None if there is no last value.
-///
 fn is_min_change(
     val: &Value,
     last_value: &Option<(SystemTime, Value)>,
@@ -505,7 +1223,7 @@ fn is_min_change(
         if let Some((_time, value)) = last_value {
             let num = value_as_number(val)?;
             let as_number = value_as_number(value)?;
-            return Ok((as_number - num).abs() >= SerdeNumber(filter_min_change.clone()));
+            return Ok((as_number - num).abs() >= FilterNumber::from(filter_min_change));
         }
     }
 
@@ -513,100 +1231,183 @@ fn is_min_change(
     Ok(true)
 }
 
-#[derive(Clone, Debug)]
-struct SerdeNumber(Number);
+///
+/// Value equals one of the `oneOf` values.
+///
+fn is_one_of(val: &Value, filters: &Filters) -> bool {
+    filters
+        .one_of
+        .as_ref()
+        .map(|values| values.contains(val))
+        .unwrap_or(true)
+}
+
+///
+/// Boolean value changed in the direction of the `edge` filter.
+/// The first value is not an edge, since there is no previous value.
+///
+fn is_edge(
+    val: &Value,
+    last_value: &Option<(SystemTime, Value)>,
+    filters: &Filters,
+) -> Result<bool, Error> {
+    let edge = match filters.edge {
+        Some(edge) => edge,
+        None => return Ok(true),
+    };
+
+    let current = val.as_bool().ok_or(Error::ValueIsNotABool)?;
+    let last = match last_value {
+        Some((_time, value)) => value.as_bool().ok_or(Error::ValueIsNotABool)?,
+        None => return Ok(false),
+    };
 
-impl SerdeNumber {
+    Ok(match edge {
+        Edge::Rising => !last && current,
+        Edge::Falling => last && !current,
+        Edge::Both => last != current,
+    })
+}
+
+///
+/// JSON merge patch (RFC 7386) that transforms `from` into `to`.
+/// Values that are not objects are replaced as a whole.
+///
+pub fn deep_diff(from: &Value, to: &Value) -> Value {
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => {
+            let mut patch = Map::new();
+            for (key, value) in to {
+                match from.get(key) {
+                    Some(previous) if previous == value => {}
+                    Some(previous) => {
+                        patch.insert(key.clone(), deep_diff(previous, value));
+                    }
+                    None => {
+                        patch.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            for key in from.keys().filter(|key| !to.contains_key(*key)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            Value::Object(patch)
+        }
+        _ => to.clone(),
+    }
+}
+
+///
+/// Number of a signal value or filter, compared and subtracted exactly across
+/// unsigned, signed and floating point JSON numbers.
+///
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum FilterNumber {
+    /// Any `u64` or `i64`
+    Int(i128),
+    /// Always finite, JSON numbers can not be NaN or infinite
+    Float(f64),
+}
+
+impl FilterNumber {
     fn abs(self) -> Self {
-        if self.0.is_u64() {
-            self
-        } else if self.0.is_i64() {
-            Self(self.0.as_i64().unwrap_or(9999).abs().into())
-        } else {
-            Self(
-                Number::from_f64(self.0.as_f64().unwrap_or_default().abs()).unwrap_or_else(|| {
-                    Number::from_f64(0.0).expect("Unexpected number conversion error")
-                }),
-            )
+        match self {
+            FilterNumber::Int(i) => FilterNumber::Int(i.abs()),
+            FilterNumber::Float(f) => FilterNumber::Float(f.abs()),
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            FilterNumber::Int(i) => i as f64,
+            FilterNumber::Float(f) => f,
         }
     }
 }
 
-impl PartialEq for SerdeNumber {
-    fn eq(&self, other: &Self) -> bool {
-        if self.0.is_u64() && other.0.is_u64() {
-            self.0.as_u64() == other.0.as_u64()
-        } else if self.0.is_i64() && other.0.is_i64() {
-            self.0.as_i64() == other.0.as_i64()
+impl From<&Number> for FilterNumber {
+    fn from(num: &Number) -> Self {
+        if let Some(u) = num.as_u64() {
+            FilterNumber::Int(i128::from(u))
+        } else if let Some(i) = num.as_i64() {
+            FilterNumber::Int(i128::from(i))
         } else {
-            self.0.as_f64() == other.0.as_f64()
+            FilterNumber::Float(num.as_f64().unwrap_or_default())
         }
     }
 }
 
-impl Eq for SerdeNumber {}
+///
+/// Compare an integer with a float without rounding the integer to the nearest float.
+///
+fn cmp_int_float(i: i128, f: f64) -> Option<Ordering> {
+    // 2^127, the bounds of i128
+    const I128_BOUND: f64 = 170_141_183_460_469_231_731_687_303_715_884_105_728.0;
+    if f.is_nan() {
+        None
+    } else if f >= I128_BOUND {
+        Some(Ordering::Less)
+    } else if f < -I128_BOUND {
+        Some(Ordering::Greater)
+    } else {
+        // The integral part of f is exactly representable as i128
+        let integral = f.trunc();
+        let fraction = f - integral;
+        Some(i.cmp(&(integral as i128)).then_with(|| {
+            if fraction > 0.0 {
+                Ordering::Less
+            } else if fraction < 0.0 {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        }))
+    }
+}
+
+impl PartialEq for FilterNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
 
-impl PartialOrd for SerdeNumber {
+impl PartialOrd for FilterNumber {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self.0.is_u64() && other.0.is_u64() {
-            self.0
-                .as_u64()
-                .unwrap_or_default()
-                .partial_cmp(&other.0.as_u64().unwrap_or_default())
-        } else if self.0.is_i64() && other.0.is_i64() {
-            self.0
-                .as_i64()
-                .unwrap_or_default()
-                .partial_cmp(&other.0.as_i64().unwrap_or_default())
-        } else {
-            self.0
-                .as_f64()
-                .as_ref()
-                .and_then(|x| other.0.as_f64().as_ref().and_then(|y| x.partial_cmp(y)))
+        match (*self, *other) {
+            (FilterNumber::Int(a), FilterNumber::Int(b)) => Some(a.cmp(&b)),
+            (FilterNumber::Int(a), FilterNumber::Float(b)) => cmp_int_float(a, b),
+            (FilterNumber::Float(a), FilterNumber::Int(b)) => {
+                cmp_int_float(b, a).map(Ordering::reverse)
+            }
+            (FilterNumber::Float(a), FilterNumber::Float(b)) => a.partial_cmp(&b),
         }
     }
 }
 
-impl Ord for SerdeNumber {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap_or(Ordering::Equal)
+impl Add for FilterNumber {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        match (self, other) {
+            (FilterNumber::Int(a), FilterNumber::Int(b)) => a
+                .checked_add(b)
+                .map(FilterNumber::Int)
+                .unwrap_or_else(|| FilterNumber::Float(a as f64 + b as f64)),
+            (a, b) => FilterNumber::Float(a.as_f64() + b.as_f64()),
+        }
     }
 }
 
-impl Sub for SerdeNumber {
+impl Sub for FilterNumber {
     type Output = Self;
 
-    #[allow(clippy::suspicious_arithmetic_impl)]
     fn sub(self, other: Self) -> Self {
-        if self.0.is_u64() && other.0.is_u64() {
-            Self(
-                (self
-                    .0
-                    .as_u64()
-                    .unwrap_or_default()
-                    .wrapping_sub(other.0.as_u64().unwrap_or_default()))
-                .into(),
-            )
-        } else if self.0.is_i64() && other.0.is_i64() {
-            Self(
-                (self
-                    .0
-                    .as_i64()
-                    .unwrap_or_default()
-                    .wrapping_sub(other.0.as_i64().unwrap_or_default()))
-                .into(),
-            )
-        } else {
-            Self(
-                Number::from_f64(
-                    self.0
-                        .as_f64()
-                        .unwrap_or_default()
-                        .abs()
-                        .sub(other.0.as_f64().unwrap_or_default().abs()),
-                )
-                .unwrap_or_else(|| 0.into()),
-            )
+        match (self, other) {
+            (FilterNumber::Int(a), FilterNumber::Int(b)) => a
+                .checked_sub(b)
+                .map(FilterNumber::Int)
+                .unwrap_or_else(|| FilterNumber::Float(a as f64 - b as f64)),
+            (a, b) => FilterNumber::Float(a.as_f64() - b.as_f64()),
         }
     }
 }
@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! VISS v2 HTTP transport.
+//! `GET /Vehicle/Speed` reads and `POST /Vehicle/Speed` with a `{ "value": ... }` body sets a signal.
+//! Requests are handled by the same `SignalManager` actions as websocket requests, responses are
+//! in the VISS v2 format and error responses are returned with the HTTP status of their error `number`.
+//!
+
+use actix_web::http::header::{HeaderName, AUTHORIZATION, CONTENT_TYPE};
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde_json::{from_slice, json, Value};
+use std::net::IpAddr;
+use uuid::Uuid;
+
+use crate::action;
+use crate::action::authorize::{authorize, authorize_connection};
+use crate::action::{Authorization, ClientMessage, ConnectionInfo};
+use crate::api_error::{new_deserialization_error, new_set_error, ActionErrorResponse};
+use crate::api_type::{ActionPath, ReqID};
+use crate::codec::Codec;
+use crate::outbound::{Outbound, Outbox, SlowConsumerPolicy};
+use crate::router::{AppState, ClientAddr};
+
+#[cfg(test)]
+mod tests {
+    use crate::action::authorize::{Authorizer, Grant, Tokens, MAX_FAILED_AUTHORIZE_ATTEMPTS};
+    use crate::api_error::{
+        ActionError, KnownError, UNAUTHORIZED_TOO_MANY_ATTEMPTS, UNAUTHORIZED_USER_TOKEN_INVALID,
+    };
+    use crate::http_api::*;
+    use actix::System;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn action_path_from_url_path() {
+        assert_eq!(
+            ActionPath::new("Vehicle.Cabin.Door"),
+            action_path("Vehicle/Cabin/Door/")
+        );
+    }
+
+    #[test]
+    fn tokens_from_headers() {
+        let r = TestRequest::default()
+            .header(AUTHORIZATION, "Bearer user")
+            .header("www-vehicle-device", "device")
+            .to_http_request();
+        assert_eq!(
+            Some(json!({ "authorization": "user", "www-vehicle-device": "device" })),
+            tokens(&r)
+        );
+        assert_eq!(None, tokens(&TestRequest::default().to_http_request()));
+    }
+
+    struct RejectingAuthorizer;
+
+    impl Authorizer for RejectingAuthorizer {
+        fn authorize(&self, _tokens: &Tokens) -> Result<Grant, KnownError> {
+            Err(UNAUTHORIZED_USER_TOKEN_INVALID)
+        }
+    }
+
+    fn authorize_error(request: TestRequest, state: &AppState) -> ActionError {
+        match authorization(state, &request.to_http_request(), ReqID::default()) {
+            Err(ActionErrorResponse::Authorize { error, .. }) => error,
+            _ => panic!("Expected an authorize error"),
+        }
+    }
+
+    #[test]
+    fn authorization_limits_rejected_attempts_per_peer() {
+        System::new("http").block_on(async {
+            let mut state = AppState::default();
+            state.set_authorizer(RejectingAuthorizer);
+            let request = |peer: &str| {
+                TestRequest::default()
+                    .peer_addr(peer.parse().unwrap())
+                    .header(AUTHORIZATION, "Bearer guess")
+            };
+
+            for _ in 0..MAX_FAILED_AUTHORIZE_ATTEMPTS {
+                assert_eq!(
+                    ActionError::from(UNAUTHORIZED_USER_TOKEN_INVALID),
+                    authorize_error(request("10.0.0.1:1000"), &state)
+                );
+            }
+            // Also from a new connection of the same peer
+            assert_eq!(
+                ActionError::from(UNAUTHORIZED_TOO_MANY_ATTEMPTS),
+                authorize_error(request("10.0.0.1:1001"), &state)
+            );
+            assert_eq!(
+                ActionError::from(UNAUTHORIZED_USER_TOKEN_INVALID),
+                authorize_error(request("10.0.0.2:1000"), &state)
+            );
+        });
+    }
+}
+
+#[derive(Deserialize)]
+struct SetBody {
+    value: Value,
+}
+
+/// `GET /{path}`
+pub(crate) async fn get(
+    state: web::Data<AppState>,
+    r: HttpRequest,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let request_id = ReqID::default();
+    let authorization = match authorization(&state, &r, request_id) {
+        Ok(authorization) => authorization,
+        Err(error) => return error_response(Codec::VissV2, error),
+    };
+
+    let (client_addr, outbox) = client_addr();
+    state.signal_manager_addr().do_send(ClientMessage {
+        client_connection_id: client_addr.client_connection_id(),
+        client_addr,
+        authorization,
+        message: action::Get {
+            path: action_path(&path),
+            request_id,
+        },
+    });
+    respond(outbox).await
+}
+
+/// `POST /{path}` with a `{ "value": ... }` body
+pub(crate) async fn set(
+    state: web::Data<AppState>,
+    r: HttpRequest,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> HttpResponse {
+    let request_id = ReqID::default();
+    let authorization = match authorization(&state, &r, request_id) {
+        Ok(authorization) => authorization,
+        Err(error) => return error_response(Codec::VissV2, error),
+    };
+
+    let value = match from_slice::<SetBody>(&body) {
+        Ok(body) => body.value,
+        Err(e) => {
+            debug!("Rejecting HTTP set request body: {}", e);
+            return error_response(
+                Codec::VissV2,
+                new_set_error(request_id, new_deserialization_error()),
+            );
+        }
+    };
+
+    let (client_addr, outbox) = client_addr();
+    state.signal_manager_addr().do_send(ClientMessage {
+        client_connection_id: client_addr.client_connection_id(),
+        client_addr,
+        authorization,
+        message: action::Set {
+            path: action_path(&path),
+            value,
+            request_id,
+        },
+    });
+    respond(outbox).await
+}
+
+///
+/// Each HTTP request is handled like a client session with a single request.
+/// Responses are never dropped by the `DropOldest` policy, the budget only applies to notifications.
+///
+fn client_addr() -> (ClientAddr, Outbox) {
+    let outbox = Outbox::new(0, SlowConsumerPolicy::DropOldest, Default::default());
+    (
+        ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::VissV2),
+        outbox,
+    )
+}
+
+async fn respond(outbox: Outbox) -> HttpResponse {
+    match outbox.recv().await {
+        Some(outbound) => http_response(outbound),
+        None => HttpResponse::ServiceUnavailable().finish(),
+    }
+}
+
+/// Error response of a request that was rejected before it was dispatched, in the format of the `codec`.
+pub(crate) fn error_response(codec: Codec, error: ActionErrorResponse) -> HttpResponse {
+    http_response(codec.encode(error.into()))
+}
+
+pub(crate) fn http_response(outbound: Outbound) -> HttpResponse {
+    HttpResponse::build(outbound.status())
+        .header(CONTENT_TYPE, "application/json")
+        .body(outbound.into_text())
+}
+
+/// `Vehicle/Cabin/Door` -> `Vehicle.Cabin.Door`
+pub(crate) fn action_path(path: &str) -> ActionPath {
+    ActionPath::new(&path.trim_matches('/').replace('/', "."))
+}
+
+///
+/// Tokens of the `Authorization: Bearer <token>` and `www-vehicle-device` headers,
+/// in the format of the `tokens` of an AUTHORIZE request.
+///
+fn tokens(r: &HttpRequest) -> Option<Value> {
+    let header = |name: &HeaderName| r.headers().get(name).and_then(|v| v.to_str().ok());
+    let user = header(&AUTHORIZATION).map(|token| token.trim_start_matches("Bearer ").trim());
+    let device = header(&HeaderName::from_static("www-vehicle-device"));
+    if user.is_none() && device.is_none() {
+        return None;
+    }
+    Some(json!({ "authorization": user, "www-vehicle-device": device }))
+}
+
+///
+/// Client whose rejected authorize attempts are counted across HTTP requests.
+///
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Peer {
+    ClientIdentity(String),
+    User(u32),
+    Address(IpAddr),
+    Unknown,
+}
+
+impl Peer {
+    fn new(r: &HttpRequest, connection: &ConnectionInfo) -> Self {
+        if let Some(ref identity) = connection.client_identity {
+            Peer::ClientIdentity(identity.common_name.clone())
+        } else if let Some(credentials) = connection.peer_credentials {
+            Peer::User(credentials.uid)
+        } else if let Some(addr) = r.peer_addr() {
+            Peer::Address(addr.ip())
+        } else {
+            Peer::Unknown
+        }
+    }
+}
+
+///
+/// Authorize the request like a websocket session. Requests without tokens are authorized by the
+/// TLS client certificate or Unix domain socket peer credentials of the connection, if accepted,
+/// requests with tokens as an AUTHORIZE request of the session.
+/// Rejected tokens are counted per `Peer`, see `Config::authorize_lockout`.
+///
+pub(crate) fn authorization(
+    state: &AppState,
+    r: &HttpRequest,
+    request_id: ReqID,
+) -> Result<Option<Authorization>, ActionErrorResponse> {
+    let connection = ConnectionInfo::from_request(r);
+    let tokens = match tokens(r) {
+        None => return Ok(authorize_connection(state.authorizer(), &connection)),
+        Some(tokens) => tokens,
+    };
+
+    let peer = Peer::new(r, &connection);
+    let mut failed_attempts = state.failed_authorize_attempts(&peer);
+    let before = failed_attempts;
+    let authorization = authorize(
+        state.authorizer(),
+        &mut failed_attempts,
+        &tokens,
+        request_id,
+        &connection,
+    );
+    if failed_attempts > before {
+        warn!(
+            "HTTP client {:?} failed to authorize, {} rejected attempts",
+            peer, failed_attempts
+        );
+        state.record_failed_authorize_attempt(peer);
+    }
+    authorization.map(Some)
+}
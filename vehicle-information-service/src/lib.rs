@@ -59,18 +59,42 @@ extern crate log;
 #[macro_use]
 extern crate serde_derive;
 
+mod access_control;
 mod action;
 pub mod api_error;
 pub mod api_type;
+mod codec;
+mod config;
 mod filter;
+mod http_api;
+pub mod metadata;
+mod outbound;
 mod router;
 mod signal_manager;
+mod sse;
+mod subscription_index;
+#[cfg(all(test, feature = "tls"))]
+#[path = "../tests/common/certificate.rs"]
+mod test_certificate;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(unix)]
+pub mod uds;
 
+pub use access_control::{Access, AccessControl, AccessRule};
+pub use action::authorize::{Authorizer, ClientIdentity, Grant, PeerCredentials, Tokens};
+pub use action::get::GetRequest;
 pub use action::set::Set;
 pub use api_error::KnownError;
 pub use api_type::ActionPath;
+pub use codec::{PROTOCOL_LEGACY, PROTOCOL_VISS_V2};
+pub use config::Config;
+pub use metadata::Metadata;
+pub use outbound::{OutboundStats, SlowConsumerPolicy};
 pub use router::{AppState, Router};
-pub use signal_manager::{SignalManager, UpdateSignal};
+pub use signal_manager::{
+    GetSubscriptionUsage, InvalidateSignal, SignalManager, SubscriptionUsage, UpdateSignal,
+};
 
 use serde_json::to_string;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -0,0 +1,500 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! Signal metadata as defined by the Vehicle Signal Specification (VSS).
+//! The metadata tree is loaded from a VSS JSON export, e.g. created with `vspec2json`.
+//!
+
+use actix::prelude::*;
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+use std::io;
+use std::str::FromStr;
+
+use crate::api_type::ActionPath;
+use crate::filter::FilterNumber;
+use crate::signal_manager::SignalManager;
+
+#[cfg(test)]
+mod tests {
+    use crate::metadata::*;
+    use serde_json::json;
+
+    fn metadata() -> Metadata {
+        r#"{
+            "Vehicle": {
+                "type": "branch",
+                "description": "High-level vehicle data.",
+                "uuid": "ccc825f94139544dbb5f4bfd033bece6",
+                "children": {
+                    "Speed": {
+                        "type": "sensor",
+                        "datatype": "float",
+                        "unit": "km/h",
+                        "min": 0,
+                        "max": 250,
+                        "description": "Vehicle speed."
+                    },
+                    "Powertrain": {
+                        "type": "branch",
+                        "description": "Powertrain data.",
+                        "children": {
+                            "Transmission": {
+                                "type": "branch",
+                                "description": "Transmission data.",
+                                "children": {
+                                    "PerformanceMode": {
+                                        "type": "actuator",
+                                        "datatype": "string",
+                                        "enum": ["NORMAL", "SPORT", "ECONOMY"],
+                                        "description": "Performance mode."
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }"#
+        .parse()
+        .expect("Invalid VSS JSON")
+    }
+
+    #[test]
+    fn node_lookup_is_case_insensitive() {
+        let metadata = metadata();
+        let speed = metadata.node(&"vehicle.speed".into()).unwrap();
+        assert_eq!(NodeType::Sensor, speed.node_type);
+        assert_eq!(Some("km/h".to_string()), speed.unit);
+        assert_eq!(Some(250.into()), speed.max);
+        assert!(metadata.node(&"Vehicle.Invalid".into()).is_none());
+    }
+
+    #[test]
+    fn enum_is_read_as_allowed_values() {
+        let metadata = metadata();
+        let mode = metadata
+            .node(&"Vehicle.Powertrain.Transmission.PerformanceMode".into())
+            .unwrap();
+        assert_eq!(
+            Some(vec![json!("NORMAL"), json!("SPORT"), json!("ECONOMY")]),
+            mode.allowed
+        );
+    }
+
+    #[test]
+    fn validate_datatype() {
+        let metadata = metadata();
+        let speed = metadata.node(&"Vehicle.Speed".into()).unwrap();
+        assert!(speed.validate(&json!(100.5)).is_ok());
+        assert!(speed.validate(&json!("100")).is_err());
+    }
+
+    #[test]
+    fn validate_integer_datatype_bounds() {
+        let node = MetadataNode {
+            node_type: NodeType::Actuator,
+            datatype: Some("uint8".to_string()),
+            unit: None,
+            min: None,
+            max: None,
+            allowed: None,
+            description: None,
+            children: Default::default(),
+        };
+        assert!(node.validate(&json!(255)).is_ok());
+        assert!(node.validate(&json!(256)).is_err());
+        assert!(node.validate(&json!(-1)).is_err());
+        assert!(node.validate(&json!(1.5)).is_err());
+    }
+
+    #[test]
+    fn validate_min_max() {
+        let metadata = metadata();
+        let speed = metadata.node(&"Vehicle.Speed".into()).unwrap();
+        assert!(speed.validate(&json!(0)).is_ok());
+        assert!(speed.validate(&json!(250.0)).is_ok());
+        assert!(speed.validate(&json!(-0.1)).is_err());
+        assert!(speed.validate(&json!(251)).is_err());
+    }
+
+    #[test]
+    fn validate_min_max_exact() {
+        // 2^53 + 1 rounds to 2^53 as f64
+        let two_pow_53 = 9_007_199_254_740_992u64;
+        let node = |min: Value, max: Value| MetadataNode {
+            node_type: NodeType::Sensor,
+            datatype: Some("uint64".to_string()),
+            unit: None,
+            min: min.as_number().cloned(),
+            max: max.as_number().cloned(),
+            allowed: None,
+            description: None,
+            children: Default::default(),
+        };
+        let max_float = node(Value::Null, json!(two_pow_53 as f64));
+        assert!(max_float.validate(&json!(two_pow_53)).is_ok());
+        assert!(max_float.validate(&json!(two_pow_53 + 1)).is_err());
+        let min_signed = node(json!(-1), Value::Null);
+        assert!(min_signed.validate(&json!(u64::MAX)).is_ok());
+        let max_signed = node(Value::Null, json!(i64::MAX));
+        assert!(max_signed.validate(&json!(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn validate_allowed_values() {
+        let metadata = metadata();
+        let mode = metadata
+            .node(&"Vehicle.Powertrain.Transmission.PerformanceMode".into())
+            .unwrap();
+        assert!(mode.validate(&json!("SPORT")).is_ok());
+        assert!(mode.validate(&json!("RACE")).is_err());
+    }
+
+    #[test]
+    fn validate_array_elements() {
+        let node = MetadataNode {
+            node_type: NodeType::Attribute,
+            datatype: Some("string[]".to_string()),
+            unit: None,
+            min: None,
+            max: None,
+            allowed: Some(vec![json!("A"), json!("B")]),
+            description: None,
+            children: Default::default(),
+        };
+        assert!(node.validate(&json!(["A", "B"])).is_ok());
+        assert!(node.validate(&json!(["A", "C"])).is_err());
+        assert!(node.validate(&json!("A")).is_err());
+    }
+
+    #[test]
+    fn subtree_contains_ancestors_of_path() {
+        let subtree = metadata().subtree(&"Vehicle.Speed".into()).unwrap();
+        assert_eq!(
+            json!({
+                "Vehicle": {
+                    "type": "branch",
+                    "description": "High-level vehicle data.",
+                    "children": {
+                        "Speed": {
+                            "type": "sensor",
+                            "datatype": "float",
+                            "unit": "km/h",
+                            "min": 0,
+                            "max": 250,
+                            "description": "Vehicle speed."
+                        }
+                    }
+                }
+            }),
+            subtree
+        );
+    }
+
+    #[test]
+    fn subtree_contains_branch_children() {
+        let subtree = metadata().subtree(&"Vehicle.Powertrain".into()).unwrap();
+        assert!(
+            subtree["Vehicle"]["children"]["Powertrain"]["children"]["Transmission"]["children"]
+                ["PerformanceMode"]
+                .is_object()
+        );
+        assert!(subtree["Vehicle"]["children"]["Speed"].is_null());
+    }
+
+    #[test]
+    fn visible_subtree_omits_hidden_signals() {
+        let metadata = metadata();
+        let speed: ActionPath = "Vehicle.Speed".into();
+        let subtree = metadata
+            .visible_subtree(&"Vehicle".into(), |path| *path == speed)
+            .unwrap();
+        assert!(subtree["Vehicle"]["children"]["Speed"].is_object());
+        assert!(subtree["Vehicle"]["children"]["Powertrain"].is_null());
+
+        assert!(metadata
+            .visible_subtree(&"Vehicle.Powertrain".into(), |path| *path == speed)
+            .is_none());
+        assert!(metadata
+            .visible_subtree(&"Vehicle".into(), |_| false)
+            .is_none());
+    }
+}
+
+///
+/// VSS node type
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeType {
+    Branch,
+    Sensor,
+    Actuator,
+    Attribute,
+}
+
+///
+/// Metadata of a single VSS node, branches contain their child nodes.
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetadataNode {
+    #[serde(rename = "type")]
+    pub node_type: NodeType,
+    /// VSS datatype e.g. `uint8`, `float`, `boolean`, `string` or `string[]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datatype: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<Number>,
+    /// Allowed values, older VSS versions name this `enum`
+    #[serde(default, alias = "enum", skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub children: BTreeMap<String, MetadataNode>,
+}
+
+impl MetadataNode {
+    /// Child node by name, names are compared case insensitive like `ActionPath`s.
+    pub fn child(&self, name: &str) -> Option<(&String, &MetadataNode)> {
+        find_node(&self.children, name)
+    }
+
+    ///
+    /// Check that the value can be set for this node according to its datatype,
+    /// `min`, `max` and `allowed` values. Returns a description of the violation if not.
+    ///
+    pub fn validate(&self, value: &Value) -> Result<(), String> {
+        let datatype = match self.datatype {
+            Some(ref datatype) => datatype.as_str(),
+            None => return Ok(()),
+        };
+
+        if let Some(element_datatype) = datatype.strip_suffix("[]") {
+            let elements = value
+                .as_array()
+                .ok_or_else(|| format!("Expected an array of `{}`", element_datatype))?;
+            elements
+                .iter()
+                .try_for_each(|element| self.validate_element(element_datatype, element))
+        } else {
+            self.validate_element(datatype, value)
+        }
+    }
+
+    fn validate_element(&self, datatype: &str, value: &Value) -> Result<(), String> {
+        let is_datatype = match datatype {
+            "boolean" => value.is_boolean(),
+            "string" => value.is_string(),
+            "float" | "double" => value.is_number(),
+            "uint8" => is_integer_in(value, u8::MIN.into(), u8::MAX.into()),
+            "uint16" => is_integer_in(value, u16::MIN.into(), u16::MAX.into()),
+            "uint32" => is_integer_in(value, u32::MIN.into(), u32::MAX.into()),
+            "uint64" => value.is_u64(),
+            "int8" => is_integer_in(value, i8::MIN.into(), i8::MAX.into()),
+            "int16" => is_integer_in(value, i16::MIN.into(), i16::MAX.into()),
+            "int32" => is_integer_in(value, i32::MIN.into(), i32::MAX.into()),
+            "int64" => value.is_i64(),
+            // Unknown datatypes e.g. structs are not validated
+            _ => true,
+        };
+        if !is_datatype {
+            return Err(format!("Value {} is not of datatype `{}`", value, datatype));
+        }
+
+        if let Value::Number(ref number) = value {
+            // Compared exactly like the subscription filters
+            let number = FilterNumber::from(number);
+            if let Some(ref min) = self.min {
+                if number < FilterNumber::from(min) {
+                    return Err(format!("Value {} is below minimum {}", value, min));
+                }
+            }
+            if let Some(ref max) = self.max {
+                if number > FilterNumber::from(max) {
+                    return Err(format!("Value {} is above maximum {}", value, max));
+                }
+            }
+        }
+
+        if let Some(ref allowed) = self.allowed {
+            if !allowed.contains(value) {
+                return Err(format!(
+                    "Value {} is not one of the allowed values {}",
+                    value,
+                    Value::Array(allowed.clone())
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// This node with only the signals below it for which `visible` returns `true`
+    /// and the branches leading to them, `None` if no signal is visible.
+    ///
+    fn visible<F>(&self, path: &ActionPath, visible: &F) -> Option<Self>
+    where
+        F: Fn(&ActionPath) -> bool,
+    {
+        if self.children.is_empty() {
+            return if visible(path) {
+                Some(self.clone())
+            } else {
+                None
+            };
+        }
+
+        let children: BTreeMap<String, MetadataNode> = self
+            .children
+            .iter()
+            .filter_map(|(name, child)| {
+                let child_path = ActionPath(format!("{}.{}", path, name));
+                child
+                    .visible(&child_path, visible)
+                    .map(|child| (name.clone(), child))
+            })
+            .collect();
+        if children.is_empty() {
+            None
+        } else {
+            Some(Self {
+                children,
+                ..self.without_children()
+            })
+        }
+    }
+
+    fn without_children(&self) -> Self {
+        Self {
+            node_type: self.node_type,
+            datatype: self.datatype.clone(),
+            unit: self.unit.clone(),
+            min: self.min.clone(),
+            max: self.max.clone(),
+            allowed: self.allowed.clone(),
+            description: self.description.clone(),
+            children: BTreeMap::new(),
+        }
+    }
+}
+
+///
+/// VSS metadata tree, keyed by the names of the root nodes e.g. `Vehicle`.
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata(pub BTreeMap<String, MetadataNode>);
+
+impl Metadata {
+    /// Load the metadata from a VSS JSON export.
+    pub fn from_reader<R>(reader: R) -> io::Result<Self>
+    where
+        R: io::Read,
+    {
+        serde_json::from_reader(reader).map_err(Into::into)
+    }
+
+    /// Metadata node of the given path.
+    pub fn node(&self, path: &ActionPath) -> Option<&MetadataNode> {
+        let mut segments = path.segments();
+        let root = segments.next()?;
+        let (_, node) = find_node(&self.0, root)?;
+        segments.try_fold(node, |node, segment| {
+            node.child(segment).map(|(_, child)| child)
+        })
+    }
+
+    ///
+    /// Metadata of the given path in VSS JSON format, including all nodes below the path.
+    /// Ancestors of the node are included as well but only contain the requested node as child,
+    /// e.g. `{ "Vehicle": { "type": "branch", "children": { "Speed": { ... } } } }` for `Vehicle.Speed`.
+    ///
+    pub fn subtree(&self, path: &ActionPath) -> Option<Value> {
+        self.visible_subtree(path, |_| true)
+    }
+
+    ///
+    /// Like `subtree`, but only containing the signals for which `visible` returns `true`,
+    /// e.g. the signals a client has access to. `None` if no signal of the subtree is visible.
+    ///
+    pub fn visible_subtree<F>(&self, path: &ActionPath, visible: F) -> Option<Value>
+    where
+        F: Fn(&ActionPath) -> bool,
+    {
+        let mut ancestors = Vec::new();
+        let mut names = Vec::new();
+        let mut nodes = &self.0;
+        for segment in path.segments() {
+            let (name, node) = find_node(nodes, segment)?;
+            ancestors.push((name, node));
+            names.push(name.as_str());
+            nodes = &node.children;
+        }
+
+        let (name, node) = ancestors.pop()?;
+        let node = node.visible(&ActionPath(names.join(".")), &visible)?;
+        let mut subtree = (name.clone(), serde_json::to_value(node).ok()?);
+        while let Some((name, node)) = ancestors.pop() {
+            let mut ancestor = serde_json::to_value(node.without_children()).ok()?;
+            let mut children = Map::new();
+            children.insert(subtree.0, subtree.1);
+            ancestor
+                .as_object_mut()?
+                .insert("children".to_string(), Value::Object(children));
+            subtree = (name.clone(), ancestor);
+        }
+
+        let mut root = Map::new();
+        root.insert(subtree.0, subtree.1);
+        Some(Value::Object(root))
+    }
+}
+
+impl FromStr for Metadata {
+    type Err = serde_json::Error;
+
+    /// Load the metadata from a VSS JSON string.
+    fn from_str(vss: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(vss)
+    }
+}
+
+fn is_integer_in(value: &Value, min: i64, max: i64) -> bool {
+    value
+        .as_i64()
+        .map(|value| min <= value && value <= max)
+        .unwrap_or(false)
+}
+
+fn find_node<'a>(
+    nodes: &'a BTreeMap<String, MetadataNode>,
+    name: &str,
+) -> Option<(&'a String, &'a MetadataNode)> {
+    let name = name.to_lowercase();
+    nodes.iter().find(|(key, _)| key.to_lowercase() == name)
+}
+
+///
+/// Replace the metadata tree of the `SignalManager`.
+///
+pub struct SetMetadata {
+    pub metadata: Metadata,
+}
+
+impl Message for SetMetadata {
+    type Result = ();
+}
+
+impl Handler<SetMetadata> for SignalManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetMetadata, _ctx: &mut Self::Context) {
+        self.metadata = msg.metadata;
+    }
+}
@@ -0,0 +1,426 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! Bounded per-client outbound queues.
+//! Responses and subscription notifications are queued per client session and written to the
+//! websocket when the connection is able to accept more data. If a slow client exceeds its
+//! outbound budget, the configured `SlowConsumerPolicy` is applied.
+//!
+
+use actix_http::ws::{CloseCode, CloseReason, OpCode, Parser};
+use actix_web::web::{Bytes, BytesMut};
+use futures::prelude::*;
+use futures::task::{Context, Poll, Waker};
+use http::status::StatusCode;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::api_type::{ActionPath, SubscriptionID};
+
+#[cfg(test)]
+mod tests {
+    use crate::api_error::new_subscribe_error;
+    use crate::api_type::ReqID;
+    use crate::codec::Codec;
+    use crate::outbound::*;
+
+    fn notification(subscription_id: i64, text: &str) -> Outbound {
+        Outbound {
+            key: Some((SubscriptionID::SubscriptionIDInt(subscription_id), None)),
+            status: StatusCode::OK,
+            text: text.to_string(),
+        }
+    }
+
+    fn response(text: &str) -> Outbound {
+        text.to_string().into()
+    }
+
+    fn texts(outbox: &Outbox) -> Vec<String> {
+        let mut texts = Vec::new();
+        while let Some(outbound) = outbox.lock().pop() {
+            texts.push(outbound.text);
+        }
+        texts
+    }
+
+    fn outbox(policy: SlowConsumerPolicy) -> (Outbox, Arc<OutboundStats>) {
+        let stats = Arc::new(OutboundStats::default());
+        (Outbox::new(6, policy, stats.clone()), stats)
+    }
+
+    #[test]
+    fn push_within_budget() {
+        let (outbox, stats) = outbox(SlowConsumerPolicy::Disconnect);
+        outbox.push(notification(1, "aa"));
+        outbox.push(response("bbbb"));
+        assert_eq!(vec!["aa", "bbbb"], texts(&outbox));
+        assert_eq!(0, stats.dropped_messages());
+    }
+
+    #[test]
+    fn push_drop_oldest_notification() {
+        let (outbox, stats) = outbox(SlowConsumerPolicy::DropOldest);
+        outbox.push(notification(1, "aa"));
+        outbox.push(response("bb"));
+        outbox.push(notification(2, "cc"));
+        outbox.push(notification(1, "dd"));
+        assert_eq!(vec!["bb", "cc", "dd"], texts(&outbox));
+        assert_eq!(1, stats.dropped_messages());
+    }
+
+    #[test]
+    fn push_response_is_never_dropped() {
+        let (outbox, stats) = outbox(SlowConsumerPolicy::DropOldest);
+        outbox.push(response("aaaa"));
+        outbox.push(response("bbbb"));
+        outbox.push(notification(1, "cc"));
+        assert_eq!(vec!["aaaa", "bbbb"], texts(&outbox));
+        assert_eq!(1, stats.dropped_messages());
+    }
+
+    #[test]
+    fn push_coalesce_latest_per_subscription() {
+        let (outbox, stats) = outbox(SlowConsumerPolicy::CoalesceLatest);
+        outbox.push(notification(1, "aa"));
+        outbox.push(notification(2, "bb"));
+        outbox.push(notification(1, "cc"));
+        outbox.push(notification(1, "dd"));
+        assert_eq!(vec!["bb", "dd"], texts(&outbox));
+        assert_eq!(2, stats.dropped_messages());
+    }
+
+    #[test]
+    fn push_disconnect() {
+        let (outbox, stats) = outbox(SlowConsumerPolicy::Disconnect);
+        outbox.push(notification(1, "aaaa"));
+        outbox.push(notification(1, "bbbb"));
+        outbox.push(notification(1, "cc"));
+        assert!(outbox.lock().is_closed());
+        assert!(texts(&outbox).is_empty());
+        assert_eq!(1, stats.dropped_messages());
+        assert_eq!(1, stats.slow_consumer_disconnects());
+    }
+
+    #[test]
+    fn recv_error_status() {
+        let (outbox, _stats) = outbox(SlowConsumerPolicy::DropOldest);
+        outbox.push(response("aa"));
+        let error = new_subscribe_error(ReqID::default(), StatusCode::NOT_FOUND.into());
+        outbox.push(Codec::Legacy.encode(error.into()));
+        let outbound = futures::executor::block_on(outbox.recv()).unwrap();
+        assert_eq!(StatusCode::OK, outbound.status());
+        let outbound = futures::executor::block_on(outbox.recv()).unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, outbound.status());
+    }
+
+    #[test]
+    fn recv_closed() {
+        let (outbox, _stats) = outbox(SlowConsumerPolicy::Disconnect);
+        outbox.push(response("aaaaaaaa"));
+        assert!(futures::executor::block_on(outbox.recv()).is_none());
+    }
+}
+
+///
+/// What to do when a client does not consume its messages fast enough
+/// and its outbound budget is exhausted.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlowConsumerPolicy {
+    /// Drop the oldest queued subscription notifications.
+    DropOldest,
+    /// Replace queued subscription notifications with the latest value per subscription and signal,
+    /// then drop the oldest notifications if the budget is still exhausted.
+    CoalesceLatest,
+    /// Close the connection.
+    Disconnect,
+}
+
+///
+/// Outbound queue statistics of all client sessions of an `AppState`.
+///
+#[derive(Debug, Default)]
+pub struct OutboundStats {
+    dropped_messages: AtomicU64,
+    slow_consumer_disconnects: AtomicU64,
+}
+
+impl OutboundStats {
+    /// Number of subscription notifications that were dropped, because a client did not consume them fast enough.
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped_messages.load(Ordering::Relaxed)
+    }
+
+    /// Number of clients that were disconnected by the `SlowConsumerPolicy::Disconnect` policy.
+    pub fn slow_consumer_disconnects(&self) -> u64 {
+        self.slow_consumer_disconnects.load(Ordering::Relaxed)
+    }
+}
+
+///
+/// A serialized message for the client.
+///
+pub(crate) struct Outbound {
+    /// Subscription and signal of a subscription notification, `None` for responses, which are never dropped.
+    key: Option<(SubscriptionID, Option<ActionPath>)>,
+    /// HTTP status of the response, the `number` of the error for error responses.
+    status: StatusCode,
+    text: String,
+}
+
+impl Outbound {
+    pub fn new(
+        key: Option<(SubscriptionID, Option<ActionPath>)>,
+        status: StatusCode,
+        text: String,
+    ) -> Self {
+        Self { key, status, text }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+}
+
+impl From<String> for Outbound {
+    fn from(text: String) -> Self {
+        Self::new(None, StatusCode::OK, text)
+    }
+}
+
+pub(crate) struct OutboundQueue {
+    /// Maximum number of queued bytes
+    budget: usize,
+    policy: SlowConsumerPolicy,
+    messages: VecDeque<Outbound>,
+    queued_bytes: usize,
+    /// Number of notifications dropped for this client
+    dropped: u64,
+    /// Set when the client is disconnected by the `SlowConsumerPolicy::Disconnect` policy
+    closed: bool,
+    /// Wakes the `OutboundStream` of the session on new messages
+    waker: Option<Waker>,
+    stats: Arc<OutboundStats>,
+}
+
+impl OutboundQueue {
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn pop(&mut self) -> Option<Outbound> {
+        let outbound = self.messages.pop_front()?;
+        self.queued_bytes -= outbound.text.len();
+        Some(outbound)
+    }
+
+    fn push(&mut self, outbound: Outbound) {
+        if self.closed {
+            return;
+        }
+
+        if !self.fits(&outbound) {
+            match self.policy {
+                SlowConsumerPolicy::Disconnect => {
+                    self.count_dropped(self.messages.len() as u64);
+                    self.messages.clear();
+                    self.queued_bytes = 0;
+                    self.closed = true;
+                    self.stats
+                        .slow_consumer_disconnects
+                        .fetch_add(1, Ordering::Relaxed);
+                    self.wake();
+                    return;
+                }
+                SlowConsumerPolicy::CoalesceLatest => {
+                    if let Some(ref key) = outbound.key {
+                        self.remove_where(|queued| queued.key.as_ref() == Some(key));
+                    }
+                    self.drop_oldest_notifications(&outbound);
+                }
+                SlowConsumerPolicy::DropOldest => self.drop_oldest_notifications(&outbound),
+            }
+
+            // Responses exceed the budget rather than being dropped
+            if !self.fits(&outbound) && outbound.key.is_some() {
+                self.count_dropped(1);
+                return;
+            }
+        }
+
+        self.queued_bytes += outbound.text.len();
+        self.messages.push_back(outbound);
+        self.wake();
+    }
+
+    fn fits(&self, outbound: &Outbound) -> bool {
+        self.queued_bytes + outbound.text.len() <= self.budget
+    }
+
+    fn drop_oldest_notifications(&mut self, outbound: &Outbound) {
+        while !self.fits(outbound) {
+            match self.messages.iter().position(|queued| queued.key.is_some()) {
+                Some(index) => {
+                    if let Some(dropped) = self.messages.remove(index) {
+                        self.queued_bytes -= dropped.text.len();
+                        self.count_dropped(1);
+                    }
+                }
+                None => return,
+            }
+        }
+    }
+
+    fn remove_where<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Outbound) -> bool,
+    {
+        let before = self.messages.len();
+        self.messages.retain(|queued| !f(queued));
+        self.queued_bytes = self.messages.iter().map(|queued| queued.text.len()).sum();
+        self.count_dropped((before - self.messages.len()) as u64);
+    }
+
+    fn count_dropped(&mut self, count: u64) {
+        self.dropped += count;
+        self.stats
+            .dropped_messages
+            .fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+///
+/// Outbound queue of a client session, shared between the session and
+/// all actors responding to the client.
+///
+#[derive(Clone)]
+pub(crate) struct Outbox(Arc<Mutex<OutboundQueue>>);
+
+impl Outbox {
+    pub fn new(budget: usize, policy: SlowConsumerPolicy, stats: Arc<OutboundStats>) -> Self {
+        Self(Arc::new(Mutex::new(OutboundQueue {
+            budget,
+            policy,
+            messages: VecDeque::new(),
+            queued_bytes: 0,
+            dropped: 0,
+            closed: false,
+            waker: None,
+            stats,
+        })))
+    }
+
+    pub fn push(&self, outbound: Outbound) {
+        self.lock().push(outbound);
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, OutboundQueue> {
+        // The queue remains consistent even if a thread panicked while holding the lock
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    ///
+    /// Wait for the next queued message, `None` if the queue was closed.
+    /// Used to respond to single HTTP requests, websocket sessions are drained by the `OutboundStream`.
+    ///
+    pub async fn recv(&self) -> Option<Outbound> {
+        future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Poll for the next queued message, `None` if the queue was closed.
+    pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<Outbound>> {
+        let mut queue = self.lock();
+        if queue.is_closed() {
+            return Poll::Ready(None);
+        }
+        match queue.pop() {
+            Some(outbound) => Poll::Ready(Some(outbound)),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+///
+/// Websocket response body of a client session.
+/// Passes through the frames of the session context e.g. pongs, then writes queued messages of the `Outbox`,
+/// one message each time the connection polls for more data.
+///
+pub(crate) struct OutboundStream<S> {
+    inner: Pin<Box<S>>,
+    outbox: Outbox,
+    closed: bool,
+}
+
+impl<S> OutboundStream<S> {
+    pub fn new(inner: S, outbox: Outbox) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            outbox,
+            closed: false,
+        }
+    }
+}
+
+impl<S> Stream for OutboundStream<S>
+where
+    S: Stream<Item = Result<Bytes, actix_web::Error>>,
+{
+    type Item = Result<Bytes, actix_web::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.closed {
+            // Ending the body stops the session actor
+            return Poll::Ready(None);
+        }
+
+        match this.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(item)) => return Poll::Ready(Some(item)),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => {}
+        }
+
+        let mut queue = this.outbox.lock();
+        let mut buf = BytesMut::new();
+        if queue.is_closed() {
+            let reason = CloseReason {
+                code: CloseCode::Policy,
+                description: Some("Outbound budget exceeded".to_string()),
+            };
+            Parser::write_close(&mut buf, Some(reason), false);
+            this.closed = true;
+            return Poll::Ready(Some(Ok(buf.freeze())));
+        }
+
+        match queue.pop() {
+            Some(outbound) => {
+                Parser::write_message(&mut buf, outbound.text, OpCode::Text, true, false);
+                Poll::Ready(Some(Ok(buf.freeze())))
+            }
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
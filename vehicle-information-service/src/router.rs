@@ -5,15 +5,125 @@ use actix_web::{web, HttpRequest};
 use actix_web_actors::ws;
 
 use futures::prelude::*;
-use http::status::StatusCode;
-use serde_json::{from_str, json, to_string};
+use serde_json::{json, to_string};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+use crate::access_control::{AccessControl, SetAccessControl};
 use crate::action;
+use crate::action::{Authorization, Authorizer, ConnectionInfo};
 use crate::api_error::*;
 use crate::api_type::*;
-use crate::serialize_result;
-use crate::signal_manager::{SignalManager, UpdateSignal};
+use crate::codec::{Codec, Response, PROTOCOLS};
+use crate::config::Config;
+use crate::http_api;
+use crate::http_api::Peer;
+use crate::metadata::{Metadata, SetMetadata};
+use crate::outbound::{OutboundStats, OutboundStream, Outbox};
+use crate::signal_manager::{
+    GetSubscriptionUsage, InvalidateSignal, SetSignalValidity, SignalManager, SubscriptionUsage,
+    UpdateSignal,
+};
+use crate::sse;
+use crate::unix_timestamp_ms;
+
+#[cfg(test)]
+mod tests {
+    use crate::action::authorize::Grant;
+    use crate::action::PeerCredentials;
+    use crate::outbound::SlowConsumerPolicy;
+    use crate::router::*;
+    use serde_json::Value;
+
+    #[test]
+    fn set_signal_with_timestamp() {
+        System::new("set-signal").block_on(async {
+            let state = AppState::default();
+            let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+            let client_addr = ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::Legacy);
+            let path: ActionPath = "Vehicle.Speed".into();
+
+            state.signal_manager_addr().do_send(action::ClientMessage {
+                client_connection_id: client_addr.client_connection_id(),
+                client_addr: client_addr.clone(),
+                authorization: None,
+                message: action::Subscribe {
+                    path: path.clone(),
+                    request_id: ReqID::default(),
+                    filters: None,
+                    initial_value: Some(false),
+                },
+            });
+            state.set_signal_with_timestamp(path.clone(), 100, 1234);
+            state.signal_manager_addr().do_send(action::ClientMessage {
+                client_connection_id: client_addr.client_connection_id(),
+                client_addr: client_addr.clone(),
+                authorization: None,
+                message: action::Get {
+                    path,
+                    request_id: ReqID::default(),
+                },
+            });
+
+            // The notification is sent by the subscription actor and may arrive after the get response
+            let mut timestamps = std::collections::HashMap::new();
+            for _ in 0..3 {
+                let outbound = outbox.recv().await.unwrap();
+                let response: Value = serde_json::from_str(&outbound.into_text()).unwrap();
+                timestamps.insert(response["action"].clone(), response["timestamp"].clone());
+            }
+            assert_eq!(Some(&json!(1234)), timestamps.get(&json!("get")));
+            assert_eq!(Some(&json!(1234)), timestamps.get(&json!("subscription")));
+        });
+    }
+
+    struct PeerAuthorizer;
+
+    impl Authorizer for PeerAuthorizer {
+        fn authorize(&self, tokens: &action::authorize::Tokens) -> Result<Grant, KnownError> {
+            match tokens.peer_credentials {
+                Some(PeerCredentials { uid: 0, .. }) => Ok(Grant {
+                    scope: "root".to_string(),
+                    ttl: Duration::from_secs(60),
+                }),
+                _ => Err(UNAUTHORIZED_USER_TOKEN_INVALID),
+            }
+        }
+    }
+
+    #[test]
+    fn session_authorized_by_connection() {
+        System::new("session").block_on(async {
+            let state = AppState::default();
+            let session = |uid| {
+                let connection = ConnectionInfo {
+                    peer_credentials: Some(PeerCredentials { uid, gid: 0 }),
+                    ..Default::default()
+                };
+                ClientSession::new(
+                    state.signal_manager_addr().clone(),
+                    Some(Arc::new(PeerAuthorizer)),
+                    Outbox::new(1, SlowConsumerPolicy::DropOldest, Default::default()),
+                    Codec::Legacy,
+                    connection,
+                )
+            };
+
+            let root = session(0);
+            assert_eq!(
+                Some("root"),
+                root.authorization.as_ref().map(|a| a.scope.as_str())
+            );
+            // A rejected connection may still authorize with tokens
+            let user = session(1000);
+            assert_eq!(None, user.authorization);
+            assert_eq!(0, user.failed_authorize_attempts);
+        });
+    }
+}
 
 pub struct ClientSession {
     /// Each client is assigned a unique identifier after connecting.
@@ -21,15 +131,141 @@ pub struct ClientSession {
     client_connection_id: ClientConnectionId,
 
     signal_manager_addr: Addr<SignalManager>,
+
+    /// Validates tokens of AUTHORIZE requests, AUTHORIZE is not supported if this is `None`.
+    authorizer: Option<Arc<dyn Authorizer>>,
+
+    /// Scope granted by the latest successful AUTHORIZE request.
+    authorization: Option<Authorization>,
+
+    /// Number of rejected AUTHORIZE requests.
+    failed_authorize_attempts: usize,
+
+    /// Queued responses and notifications for the client.
+    outbox: Outbox,
+
+    /// Message format negotiated by the websocket subprotocol.
+    codec: Codec,
+
+    /// TLS client certificate or Unix domain socket peer credentials of the connection, passed to the `Authorizer`.
+    connection: ConnectionInfo,
+}
+
+///
+/// Address used to respond to a client session.
+/// Messages are queued in the outbound queue of the session, see `SlowConsumerPolicy`.
+///
+#[derive(Clone)]
+pub struct ClientAddr {
+    client_connection_id: ClientConnectionId,
+    outbox: Outbox,
+    codec: Codec,
+}
+
+impl ClientAddr {
+    pub(crate) fn new(
+        client_connection_id: ClientConnectionId,
+        outbox: Outbox,
+        codec: Codec,
+    ) -> Self {
+        Self {
+            client_connection_id,
+            outbox,
+            codec,
+        }
+    }
+
+    pub(crate) fn client_connection_id(&self) -> ClientConnectionId {
+        self.client_connection_id
+    }
+
+    pub(crate) fn do_send<M>(&self, msg: M)
+    where
+        M: Into<Response>,
+    {
+        self.outbox.push(self.codec.encode(msg.into()));
+    }
+}
+
+impl PartialEq for ClientAddr {
+    fn eq(&self, other: &Self) -> bool {
+        self.client_connection_id == other.client_connection_id
+    }
+}
+
+impl Eq for ClientAddr {}
+
+impl Hash for ClientAddr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.client_connection_id.hash(state);
+    }
 }
 
 impl ClientSession {
-    pub fn new(signal_manager_addr: Addr<SignalManager>) -> Self {
+    pub fn new(
+        signal_manager_addr: Addr<SignalManager>,
+        authorizer: Option<Arc<dyn Authorizer>>,
+        outbox: Outbox,
+        codec: Codec,
+        connection: ConnectionInfo,
+    ) -> Self {
+        // Authorized like an HTTP request without tokens
+        let authorization =
+            action::authorize::authorize_connection(authorizer.as_deref(), &connection);
         Self {
             client_connection_id: Uuid::new_v4(),
             signal_manager_addr,
+            authorizer,
+            authorization,
+            failed_authorize_attempts: 0,
+            outbox,
+            codec,
+            connection,
         }
     }
+
+    fn client_addr(&self) -> ClientAddr {
+        ClientAddr::new(self.client_connection_id, self.outbox.clone(), self.codec)
+    }
+
+    fn authorize(
+        &mut self,
+        tokens: &serde_json::Value,
+        request_id: ReqID,
+    ) -> Result<ActionSuccessResponse, ActionErrorResponse> {
+        let authorization = match action::authorize::authorize(
+            self.authorizer.as_deref(),
+            &mut self.failed_authorize_attempts,
+            tokens,
+            request_id,
+            &self.connection,
+        ) {
+            Ok(authorization) => authorization,
+            Err(e) => {
+                warn!(
+                    "Client {} failed to authorize, {} rejected attempts",
+                    self.client_connection_id, self.failed_authorize_attempts
+                );
+                return Err(e);
+            }
+        };
+
+        let ttl = authorization
+            .expires_at
+            .saturating_duration_since(Instant::now())
+            .as_secs();
+        info!(
+            "Client {} authorized for scope `{}`",
+            self.client_connection_id, authorization.scope
+        );
+        self.authorization = Some(authorization);
+
+        Ok(ActionSuccessResponse::Authorize {
+            request_id,
+            ttl,
+            timestamp: unix_timestamp_ms(),
+        })
+    }
 }
 
 impl Actor for ClientSession {
@@ -37,47 +273,32 @@ impl Actor for ClientSession {
 
     fn started(&mut self, _ctx: &mut Self::Context) {
         info!("Client {} started", self.client_connection_id);
+        if let Some(ref authorization) = self.authorization {
+            info!(
+                "Client {} authorized by its connection for scope `{}`",
+                self.client_connection_id, authorization.scope
+            );
+        }
     }
 
-    fn stopped(&mut self, ctx: &mut Self::Context) {
-        // Cleanup client subscriptions
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        // Cleanup client subscriptions, unless the client may resume them
         self.signal_manager_addr.do_send(action::ClientMessage {
             client_connection_id: self.client_connection_id,
-            client_addr: ctx.address(),
+            client_addr: self.client_addr(),
+            authorization: self.authorization.clone(),
             message: action::UnsubscribeAll { request_id: None },
         });
 
-        info!("Client {} stopped", self.client_connection_id);
-    }
-}
-
-impl Message for ActionSuccessResponse {
-    type Result = ();
-}
-
-impl Message for ActionErrorResponse {
-    type Result = ();
-}
-
-impl Handler<ActionSuccessResponse> for ClientSession {
-    type Result = ();
-    fn handle(&mut self, msg: ActionSuccessResponse, ctx: &mut Self::Context) {
-        // TODO replace subscribe error with subscription error
-        let serialized = serialize_result(&Ok(msg), || {
-            new_subscribe_error(ReqID::ReqIDInt(0), StatusCode::INTERNAL_SERVER_ERROR.into())
-        });
-        ctx.text(serialized)
-    }
-}
+        let dropped = self.outbox.lock().dropped();
+        if dropped > 0 {
+            warn!(
+                "Dropped {} notifications for slow client {}",
+                dropped, self.client_connection_id
+            );
+        }
 
-impl Handler<ActionErrorResponse> for ClientSession {
-    type Result = ();
-    fn handle(&mut self, msg: ActionErrorResponse, ctx: &mut Self::Context) {
-        // TODO replace subscribe error with subscription error
-        let serialized = serialize_result(&Err(msg), || {
-            new_subscribe_error(ReqID::ReqIDInt(0), StatusCode::INTERNAL_SERVER_ERROR.into())
-        });
-        ctx.text(serialized)
+        info!("Client {} stopped", self.client_connection_id);
     }
 }
 
@@ -96,12 +317,13 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ClientSession {
             Ok(ws::Message::Text(ref txt)) => {
                 // deserialize and dispatch VIS action
 
-                match from_str::<Action>(txt) {
+                match self.codec.decode(txt) {
                     Err(e) => {
                         warn!("Deserialization error {}", e);
                         let err = new_deserialization_error();
+                        // Not a response to an action, the same in all formats
                         if let Ok(serialized) = to_string(&err) {
-                            ctx.text(serialized);
+                            self.outbox.push(serialized.into());
                         }
                     }
                     Ok(action) => {
@@ -109,19 +331,36 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ClientSession {
                             "Received action {:?} for client connection_id {}",
                             action, self.client_connection_id
                         );
+
+                        if self
+                            .authorization
+                            .as_ref()
+                            .map(Authorization::is_expired)
+                            .unwrap_or(false)
+                        {
+                            info!(
+                                "Authorization of client {} expired",
+                                self.client_connection_id
+                            );
+                            self.authorization = None;
+                        }
+
                         match action {
                             Action::Subscribe {
                                 path,
                                 request_id,
                                 filters,
+                                initial_value,
                             } => {
                                 self.signal_manager_addr.do_send(action::ClientMessage {
                                     client_connection_id: self.client_connection_id,
-                                    client_addr: ctx.address(),
+                                    client_addr: self.client_addr(),
+                                    authorization: self.authorization.clone(),
                                     message: action::Subscribe {
                                         path,
                                         request_id,
                                         filters,
+                                        initial_value,
                                     },
                                 });
                             }
@@ -131,7 +370,8 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ClientSession {
                             } => {
                                 self.signal_manager_addr.do_send(action::ClientMessage {
                                     client_connection_id: self.client_connection_id,
-                                    client_addr: ctx.address(),
+                                    client_addr: self.client_addr(),
+                                    authorization: self.authorization.clone(),
                                     message: action::Unsubscribe {
                                         request_id,
                                         subscription_id,
@@ -141,14 +381,16 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ClientSession {
                             Action::Get { path, request_id } => {
                                 self.signal_manager_addr.do_send(action::ClientMessage {
                                     client_connection_id: self.client_connection_id,
-                                    client_addr: ctx.address(),
+                                    client_addr: self.client_addr(),
+                                    authorization: self.authorization.clone(),
                                     message: action::Get { request_id, path },
                                 });
                             }
                             Action::UnsubscribeAll { request_id } => {
                                 self.signal_manager_addr.do_send(action::ClientMessage {
                                     client_connection_id: self.client_connection_id,
-                                    client_addr: ctx.address(),
+                                    client_addr: self.client_addr(),
+                                    authorization: self.authorization.clone(),
                                     message: action::UnsubscribeAll {
                                         request_id: Some(request_id),
                                     },
@@ -161,7 +403,8 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ClientSession {
                             } => {
                                 self.signal_manager_addr.do_send(action::ClientMessage {
                                     client_connection_id: self.client_connection_id,
-                                    client_addr: ctx.address(),
+                                    client_addr: self.client_addr(),
+                                    authorization: self.authorization.clone(),
                                     message: action::Set {
                                         request_id,
                                         path,
@@ -169,23 +412,35 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ClientSession {
                                     },
                                 });
                             }
-                            // TODO implement
-                            Action::Authorize { request_id, .. } => {
-                                if let Ok(serialized) = to_string(&new_authorize_error(
-                                    request_id,
-                                    StatusCode::NOT_IMPLEMENTED.into(),
-                                )) {
-                                    ctx.text(serialized)
+                            Action::Authorize { tokens, request_id } => {
+                                match self.authorize(&tokens, request_id) {
+                                    Ok(response) => self.client_addr().do_send(response),
+                                    Err(response) => self.client_addr().do_send(response),
                                 }
                             }
-                            // TODO implement
-                            Action::GetMetadata { request_id, .. } => {
-                                if let Ok(serialized) = to_string(&new_get_metadata_error(
-                                    request_id,
-                                    StatusCode::NOT_IMPLEMENTED.into(),
-                                )) {
-                                    ctx.text(serialized)
-                                }
+                            Action::GetMetadata { path, request_id } => {
+                                self.signal_manager_addr.do_send(action::ClientMessage {
+                                    client_connection_id: self.client_connection_id,
+                                    client_addr: self.client_addr(),
+                                    authorization: self.authorization.clone(),
+                                    message: action::GetMetadata { path, request_id },
+                                });
+                            }
+                            Action::Resume {
+                                request_id,
+                                resume_token,
+                                replay,
+                            } => {
+                                self.signal_manager_addr.do_send(action::ClientMessage {
+                                    client_connection_id: self.client_connection_id,
+                                    client_addr: self.client_addr(),
+                                    authorization: self.authorization.clone(),
+                                    message: action::Resume {
+                                        request_id,
+                                        resume_token,
+                                        replay,
+                                    },
+                                });
                             }
                         }
                     }
@@ -209,14 +464,100 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ClientSession {
 }
 
 pub struct AppState {
+    config: Config,
     signal_manager_addr: Addr<SignalManager>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+    outbound_stats: Arc<OutboundStats>,
+    /// Rejected authorize attempts of HTTP clients and the time of the latest rejected attempt
+    failed_authorize_attempts: Mutex<HashMap<Peer, (usize, Instant)>>,
 }
 
 impl AppState {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config: config.clone(),
+            signal_manager_addr: SignalManager::new(config).start(),
+            authorizer: None,
+            outbound_stats: Default::default(),
+            failed_authorize_attempts: Default::default(),
+        }
+    }
+
     pub fn signal_manager_addr(&self) -> Addr<SignalManager> {
         self.signal_manager_addr.clone()
     }
 
+    /// Outbound queue statistics of the client sessions of this `AppState`, e.g. dropped notifications of slow clients.
+    pub fn outbound_stats(&self) -> &OutboundStats {
+        &self.outbound_stats
+    }
+
+    /// Outbound queue for a new client session.
+    pub(crate) fn new_outbox(&self) -> Outbox {
+        Outbox::new(
+            self.config.outbound_budget,
+            self.config.slow_consumer_policy,
+            self.outbound_stats.clone(),
+        )
+    }
+
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub(crate) fn authorizer(&self) -> Option<&dyn Authorizer> {
+        self.authorizer.as_deref()
+    }
+
+    /// Rejected authorize attempts of an HTTP client within the `Config::authorize_lockout`.
+    pub(crate) fn failed_authorize_attempts(&self, peer: &Peer) -> usize {
+        let mut failed_attempts = self.lock_failed_authorize_attempts();
+        let lockout = self.config.authorize_lockout;
+        failed_attempts.retain(|_, (_, latest)| latest.elapsed() < lockout);
+        failed_attempts
+            .get(peer)
+            .map(|(count, _)| *count)
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn record_failed_authorize_attempt(&self, peer: Peer) {
+        let mut failed_attempts = self.lock_failed_authorize_attempts();
+        let entry = failed_attempts.entry(peer).or_insert((0, Instant::now()));
+        *entry = (entry.0 + 1, Instant::now());
+    }
+
+    fn lock_failed_authorize_attempts(&self) -> MutexGuard<'_, HashMap<Peer, (usize, Instant)>> {
+        // The counts remain consistent even if a thread panicked while holding the lock
+        self.failed_authorize_attempts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Current number of subscriptions, per client session and per path.
+    pub async fn subscription_usage(&self) -> Result<SubscriptionUsage, MailboxError> {
+        self.signal_manager_addr.send(GetSubscriptionUsage).await
+    }
+
+    /// Replace the access control list that is checked for `get`, `set` and `subscribe` requests.
+    pub fn set_access_control(&self, access_control: AccessControl) {
+        self.signal_manager_addr
+            .do_send(SetAccessControl { access_control });
+    }
+
+    /// Replace the VSS metadata that is returned for `getMetadata` requests.
+    pub fn set_metadata(&self, metadata: Metadata) {
+        self.signal_manager_addr.do_send(SetMetadata { metadata });
+    }
+
+    /// Register the authorizer that validates the tokens of client `authorize` requests.
+    /// Without an authorizer `authorize` requests are rejected as not implemented.
+    pub fn set_authorizer<A>(&mut self, authorizer: A)
+    where
+        A: Authorizer + 'static,
+    {
+        self.authorizer = Some(Arc::new(authorizer));
+    }
+
     /// Set the path to the given value.
     pub fn set_signal<T>(&self, path: ActionPath, value: T)
     where
@@ -225,15 +566,62 @@ impl AppState {
         self.signal_manager_addr.do_send(UpdateSignal {
             path,
             value: json!(value),
+            timestamp: None,
+        });
+    }
+
+    ///
+    /// Set the path to the given value, measured by the signal source at `timestamp`
+    /// in milliseconds since the Unix epoch. The timestamp is returned in `get` responses and notifications.
+    ///
+    pub fn set_signal_with_timestamp<T>(&self, path: ActionPath, value: T, timestamp: u128)
+    where
+        T: serde::ser::Serialize,
+    {
+        self.signal_manager_addr.do_send(UpdateSignal {
+            path,
+            value: json!(value),
+            timestamp: Some(timestamp),
         });
     }
 
+    /// Mark the signal as unavailable until its next update, e.g. when the signal source failed.
+    pub fn invalidate_signal(&self, path: ActionPath) {
+        self.signal_manager_addr.do_send(InvalidateSignal { path });
+    }
+
+    /// Set the time after which the signal becomes unavailable if it is not updated,
+    /// `None` to fall back to `Config::signal_validity`.
+    pub fn set_signal_validity(&self, path: ActionPath, validity: Option<Duration>) {
+        self.signal_manager_addr
+            .do_send(SetSignalValidity { path, validity });
+    }
+
     /// Register a `set` action recipient. This recipient will receive all `set` action requests for all clients.
     pub fn add_set_recipient(&self, path: ActionPath, recipient: Recipient<action::Set>) {
         self.signal_manager_addr
             .do_send(action::AddSetRecipient { path, recipient });
     }
 
+    ///
+    /// Register a `get` provider for the given path. The provider is asked for the signal value on `get`
+    /// requests if no value is cached, or if the cached value is older than `max_age`.
+    /// With a `max_age` of `None` the provider is only asked while no value is cached.
+    /// Branch and wildcard `get` requests do not ask providers and are answered from the cache.
+    ///
+    pub fn add_get_recipient(
+        &self,
+        path: ActionPath,
+        recipient: Recipient<action::GetRequest>,
+        max_age: Option<Duration>,
+    ) {
+        self.signal_manager_addr.do_send(action::AddGetRecipient {
+            path,
+            recipient,
+            max_age,
+        });
+    }
+
     /// Spawn a new signal stream source. A signal stream will provide signal updates for the given path.
     pub fn spawn_stream_signal_source<St>(&self, path: ActionPath, s: St)
     where
@@ -250,6 +638,7 @@ impl AppState {
                 let update = UpdateSignal {
                     path: ActionPath(path.to_string()),
                     value: json!(item),
+                    timestamp: None,
                 };
                 signal_manager_addr.do_send(update);
 
@@ -276,7 +665,11 @@ impl AppState {
                 if let Ok(items) = items {
                     for item in items.source() {
                         let (path, value) = item;
-                        let update = UpdateSignal { path, value };
+                        let update = UpdateSignal {
+                            path,
+                            value,
+                            timestamp: None,
+                        };
                         signal_manager_addr.do_send(update);
                     }
                 }
@@ -305,9 +698,7 @@ impl ActionSourceable for Vec<(ActionPath, serde_json::Value)> {
 
 impl Default for AppState {
     fn default() -> Self {
-        Self {
-            signal_manager_addr: SignalManager::start_default(),
-        }
+        Self::new(Config::default())
     }
 }
 
@@ -319,7 +710,19 @@ async fn ws_index(
     stream: web::Payload,
 ) -> Result<actix_web::HttpResponse, actix_web::Error> {
     let addr = state.signal_manager_addr.clone();
-    ws::start(ClientSession::new(addr), &r, stream)
+    let outbox = state.new_outbox();
+    let codec = Codec::negotiate(&r);
+    let session = ClientSession::new(
+        addr,
+        state.authorizer.clone(),
+        outbox.clone(),
+        codec,
+        ConnectionInfo::from_request(&r),
+    );
+
+    let mut response = ws::handshake_with_protocols(&r, PROTOCOLS)?;
+    let body = ws::WebsocketContext::create(session, stream);
+    Ok(response.streaming(OutboundStream::new(body, outbox)))
 }
 
 impl Router {
@@ -327,4 +730,18 @@ impl Router {
     pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         cfg.service(web::resource("/").route(web::get().to(ws_index)));
     }
+
+    ///
+    /// Serve `get` and `set` requests over plain HTTP, e.g. `GET /Vehicle/Speed`,
+    /// and subscriptions as Server-Sent Events, e.g. `GET /subscribe?path=Vehicle.Speed`.
+    /// The routes match all paths, register them after the application's own routes.
+    ///
+    pub fn configure_http_routes(cfg: &mut web::ServiceConfig) {
+        cfg.service(web::resource("/subscribe").route(web::get().to(sse::subscribe)));
+        cfg.service(
+            web::resource("/{path:.+}")
+                .route(web::get().to(http_api::get))
+                .route(web::post().to(http_api::set)),
+        );
+    }
 }
@@ -3,28 +3,295 @@ use actix::prelude::*;
 use log::warn;
 use serde_json::Value;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
+use crate::access_control::AccessControl;
+use crate::action::get::GetProvider;
 use crate::action::set::Set;
-use crate::api_error::{ActionErrorResponse, BAD_REQUEST_FILTER_INVALID};
-use crate::api_type::{ActionPath, ActionSuccessResponse, Filters, SubscriptionID};
+use crate::api_error::{
+    ActionErrorResponse, BAD_REQUEST_FILTER_INVALID, NOT_FOUND_UNAVAILABLE_DATA,
+};
+use crate::api_type::{
+    ActionPath, ActionSuccessResponse, ClientConnectionId, Filters, ResumeToken, SubscriptionID,
+};
+use crate::codec::{DataPoint, Response};
+use crate::config::Config;
 use crate::filter;
-use crate::router::ClientSession;
+use crate::metadata::Metadata;
+use crate::router::ClientAddr;
+use crate::subscription_index::SubscriptionIndex;
 use crate::unix_timestamp_ms;
 
+#[cfg(test)]
+mod tests {
+    use crate::action::{ClientMessage, Get};
+    use crate::api_type::ReqID;
+    use crate::codec::Codec;
+    use crate::outbound::{Outbox, SlowConsumerPolicy};
+    use crate::signal_manager::*;
+    use actix::clock::delay_for;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    const VALIDITY: Duration = Duration::from_millis(100);
+
+    fn signal_manager() -> Addr<SignalManager> {
+        SignalManager::new(Config {
+            signal_validity: Some(VALIDITY),
+            ..Default::default()
+        })
+        .start()
+    }
+
+    fn update(signal_manager: &Addr<SignalManager>) {
+        signal_manager.do_send(UpdateSignal {
+            path: "Vehicle.Speed".into(),
+            value: json!(100),
+            timestamp: None,
+        });
+    }
+
+    /// Response to a `get` request of the updated signal.
+    async fn get(signal_manager: &Addr<SignalManager>) -> Value {
+        let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+        let client_addr = ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::Legacy);
+        signal_manager.do_send(ClientMessage {
+            client_connection_id: client_addr.client_connection_id(),
+            client_addr,
+            authorization: None,
+            message: Get {
+                path: "Vehicle.Speed".into(),
+                request_id: ReqID::default(),
+            },
+        });
+        let outbound = outbox.recv().await.unwrap();
+        serde_json::from_str(&outbound.into_text()).unwrap()
+    }
+
+    fn subscription(outbox: &Outbox, filters: Filters) -> Subscription {
+        Subscription {
+            client_addr: Some(ClientAddr::new(
+                Uuid::new_v4(),
+                outbox.clone(),
+                Codec::Legacy,
+            )),
+            path: "Vehicle".into(),
+            subscription_id: SubscriptionID::SubscriptionIDInt(1),
+            filters: Some(filters),
+            latest_signal_value: Default::default(),
+            last_signal_value_client: Default::default(),
+            interval_handle: None,
+            coalesce_pending: Default::default(),
+            coalesce_handle: None,
+            invalid_filter_paths: Default::default(),
+            filter_states: Default::default(),
+            sequence: 0,
+        }
+    }
+
+    /// Values of the queued notifications by signal path, in the order they were sent.
+    fn notifications(outbox: &Outbox) -> Vec<(Value, Value)> {
+        let mut notifications = Vec::new();
+        while let Some(outbound) = outbox.lock().pop() {
+            let notification: Value = serde_json::from_str(&outbound.into_text()).unwrap();
+            notifications.push((notification["path"].clone(), notification["value"].clone()));
+        }
+        notifications
+    }
+
+    #[test]
+    fn coalesce_newest_value_per_path() {
+        System::new("coalesce").block_on(async {
+            let coalesce = Duration::from_millis(50);
+            let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+            let subscription = subscription(
+                &outbox,
+                Filters {
+                    coalesce: Some(coalesce.as_millis() as u64),
+                    ..Default::default()
+                },
+            )
+            .start();
+            let notify = |path: &str, value: i64| {
+                subscription.do_send(NotifySubscriber {
+                    path: path.into(),
+                    signal_value: json!(value),
+                    timestamp: 0,
+                });
+            };
+
+            notify("Vehicle.Speed", 1);
+            notify("Vehicle.Speed", 2);
+            notify("Vehicle.RPM", 10);
+            notify("Vehicle.Speed", 3);
+            notify("Vehicle.RPM", 20);
+            delay_for(coalesce / 2).await;
+            assert!(notifications(&outbox).is_empty());
+
+            delay_for(coalesce).await;
+            let mut window = notifications(&outbox);
+            window.sort_by_key(|(path, _value)| path.to_string());
+            assert_eq!(
+                vec![
+                    (json!("Vehicle.RPM"), json!(20)),
+                    (json!("Vehicle.Speed"), json!(3)),
+                ],
+                window
+            );
+
+            // Each window is delivered separately
+            notify("Vehicle.Speed", 4);
+            notify("Vehicle.Speed", 5);
+            delay_for(coalesce * 2).await;
+            assert_eq!(
+                vec![(json!("Vehicle.Speed"), json!(5))],
+                notifications(&outbox)
+            );
+        });
+    }
+
+    #[test]
+    fn interval_latest_value_per_tick() {
+        System::new("interval").block_on(async {
+            let interval = Duration::from_millis(100);
+            let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+            let subscription = subscription(
+                &outbox,
+                Filters {
+                    interval: Some(interval.as_millis() as u64),
+                    ..Default::default()
+                },
+            )
+            .start();
+            let notify = |value: i64| {
+                subscription.do_send(NotifySubscriber {
+                    path: "Vehicle.Speed".into(),
+                    signal_value: json!(value),
+                    timestamp: 0,
+                });
+            };
+
+            notify(1);
+            notify(2);
+            delay_for(interval / 2).await;
+            assert!(notifications(&outbox).is_empty());
+
+            delay_for(interval).await;
+            assert_eq!(
+                vec![(json!("Vehicle.Speed"), json!(2))],
+                notifications(&outbox)
+            );
+
+            // Values are not sent before the next tick, unchanged values are not sent again
+            notify(3);
+            delay_for(interval / 4).await;
+            assert!(notifications(&outbox).is_empty());
+            delay_for(interval * 3 / 2).await;
+            assert_eq!(
+                vec![(json!("Vehicle.Speed"), json!(3))],
+                notifications(&outbox)
+            );
+        });
+    }
+
+    #[test]
+    fn signal_validity_expires() {
+        System::new("signal-validity").block_on(async {
+            let signal_manager = signal_manager();
+            update(&signal_manager);
+            assert_eq!(json!(100), get(&signal_manager).await["value"]);
+
+            delay_for(VALIDITY * 2).await;
+            assert_eq!(
+                json!("unavailable_data"),
+                get(&signal_manager).await["error"]["reason"]
+            );
+
+            update(&signal_manager);
+            assert_eq!(json!(100), get(&signal_manager).await["value"]);
+        });
+    }
+
+    #[test]
+    fn signal_validity_refreshed_on_update() {
+        System::new("signal-validity").block_on(async {
+            let signal_manager = signal_manager();
+            update(&signal_manager);
+            delay_for(VALIDITY / 2).await;
+            update(&signal_manager);
+
+            // The validity of the first update has expired, the second update is still valid
+            delay_for(VALIDITY * 3 / 4).await;
+            assert_eq!(json!(100), get(&signal_manager).await["value"]);
+
+            delay_for(VALIDITY).await;
+            assert_eq!(
+                json!("unavailable_data"),
+                get(&signal_manager).await["error"]["reason"]
+            );
+        });
+    }
+}
+
 #[derive(Default)]
 pub struct SignalManager {
-    pub(crate) signal_cache: HashMap<ActionPath, Value>,
+    pub(crate) config: Config,
+
+    pub(crate) signal_cache: HashMap<ActionPath, CachedSignal>,
 
-    pub(crate) addr_to_subscription_ids: HashMap<Addr<ClientSession>, Vec<SubscriptionID>>,
-    pub(crate) path_to_subscription_id: HashMap<ActionPath, Vec<SubscriptionID>>,
+    pub(crate) addr_to_subscription_ids: HashMap<ClientAddr, Vec<SubscriptionID>>,
+    pub(crate) subscription_index: SubscriptionIndex,
     pub(crate) subscription_id_to_subscription:
-        HashMap<SubscriptionID, (Addr<Subscription>, Addr<ClientSession>, ActionPath)>,
+        HashMap<SubscriptionID, (Addr<Subscription>, ClientAddr, ActionPath)>,
 
     /// Recipients that are informed on incoming `SET` actions.
     pub(crate) set_recipients: HashMap<ActionPath, Recipient<Set>>,
+
+    /// Providers that are asked for the signal value on `GET` actions if the cache entry is missing or stale.
+    pub(crate) get_providers: HashMap<ActionPath, GetProvider>,
+
+    /// Time after which a signal without updates becomes unavailable, overrides `Config::signal_validity`.
+    pub(crate) signal_validity: HashMap<ActionPath, Duration>,
+
+    /// Scheduled expiry per signal, rescheduled on each update.
+    pub(crate) validity_handles: HashMap<ActionPath, SpawnHandle>,
+
+    /// Access rules for `GET`, `SET` and `SUBSCRIBE` actions.
+    pub(crate) access_control: AccessControl,
+
+    /// VSS metadata returned by `GET_METADATA` actions.
+    pub(crate) metadata: Metadata,
+
+    /// Resume tokens of connected client sessions that requested a token.
+    pub(crate) resume_tokens: HashMap<ClientAddr, ResumeToken>,
+
+    /// Subscriptions of disconnected client sessions by resume token, kept until the grace period expires.
+    pub(crate) detached_subscription_ids: HashMap<ResumeToken, Vec<SubscriptionID>>,
+}
+
+impl SignalManager {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+}
+
+///
+/// Latest known value of a signal.
+///
+#[derive(Clone, Debug)]
+pub(crate) struct CachedSignal {
+    pub value: Value,
+    /// Unix timestamp in milliseconds when the value was measured
+    pub timestamp: u128,
+    /// Point in time the value was received
+    pub received: Instant,
+    /// `false` if the signal was invalidated or its validity has expired
+    pub available: bool,
 }
 
 impl Actor for SignalManager {
@@ -44,6 +311,9 @@ impl Supervised for SignalManager {
 pub struct UpdateSignal {
     pub path: ActionPath,
     pub value: Value,
+    /// Unix timestamp in milliseconds when the value was measured by the signal source,
+    /// `None` to use the time the update is received.
+    pub timestamp: Option<u128>,
 }
 
 impl Message for UpdateSignal {
@@ -53,36 +323,196 @@ impl Message for UpdateSignal {
 impl Handler<UpdateSignal> for SignalManager {
     type Result = ();
 
-    fn handle(&mut self, msg: UpdateSignal, _ctx: &mut Self::Context) {
-        let subscription_ids = self.path_to_subscription_id.get(&msg.path);
-
-        for subscription_id in subscription_ids.unwrap_or(&Vec::new()) {
-            match self
-                .subscription_id_to_subscription
-                .get_mut(&subscription_id)
-            {
-                None => warn!("Missing addr for SubscriptionId {}", subscription_id),
-                Some((subscription_addr, _client_session_addr, _path)) => {
-                    let notify = NotifySubscriber {
-                        signal_value: msg.value.clone(),
-                    };
-                    subscription_addr.do_send(notify);
-                }
-            }
+    fn handle(&mut self, msg: UpdateSignal, ctx: &mut Self::Context) {
+        let timestamp = msg.timestamp.unwrap_or_else(unix_timestamp_ms);
+        self.update_signal(msg.path, msg.value, timestamp, ctx);
+    }
+}
+
+///
+/// Mark a signal as unavailable e.g. when its source failed.
+/// `get` requests are answered with an `unavailable_data` error and subscribers are notified,
+/// until the next `UpdateSignal` for the path.
+///
+#[derive(Debug, Clone)]
+pub struct InvalidateSignal {
+    pub path: ActionPath,
+}
+
+impl Message for InvalidateSignal {
+    type Result = ();
+}
+
+impl Handler<InvalidateSignal> for SignalManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: InvalidateSignal, _ctx: &mut Self::Context) {
+        self.invalidate_signal(&msg.path);
+    }
+}
+
+///
+/// Set the time after which the signal becomes unavailable, if it does not receive an update.
+/// `None` removes the signal specific validity, `Config::signal_validity` applies instead.
+///
+pub struct SetSignalValidity {
+    pub path: ActionPath,
+    pub validity: Option<Duration>,
+}
+
+impl Message for SetSignalValidity {
+    type Result = ();
+}
+
+impl Handler<SetSignalValidity> for SignalManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetSignalValidity, _ctx: &mut Self::Context) {
+        match msg.validity {
+            Some(validity) => self.signal_validity.insert(msg.path, validity),
+            None => self.signal_validity.remove(&msg.path),
+        };
+    }
+}
+
+///
+/// Current number of subscriptions, to be compared against the `Config` subscription limits.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SubscriptionUsage {
+    /// All subscriptions, including subscriptions of disconnected sessions waiting to be resumed
+    pub total: usize,
+    /// Subscriptions per connected client session
+    pub per_client: HashMap<ClientConnectionId, usize>,
+    /// Subscriptions per exact subscribed path, which may be a branch or wildcard path
+    pub per_path: HashMap<ActionPath, usize>,
+}
+
+///
+/// Query the current `SubscriptionUsage`.
+///
+pub struct GetSubscriptionUsage;
+
+impl Message for GetSubscriptionUsage {
+    type Result = SubscriptionUsage;
+}
+
+impl Handler<GetSubscriptionUsage> for SignalManager {
+    type Result = MessageResult<GetSubscriptionUsage>;
+
+    fn handle(&mut self, _msg: GetSubscriptionUsage, _ctx: &mut Self::Context) -> Self::Result {
+        let mut per_path = HashMap::new();
+        for (_subscription_addr, _client_addr, path) in
+            self.subscription_id_to_subscription.values()
+        {
+            *per_path.entry(path.clone()).or_default() += 1;
         }
 
-        debug!("Updating signal cache value for path: {}", msg.path);
-        self.signal_cache.insert(msg.path, msg.value);
+        MessageResult(SubscriptionUsage {
+            total: self.subscription_id_to_subscription.len(),
+            per_client: self
+                .addr_to_subscription_ids
+                .iter()
+                .map(|(client_addr, subscription_ids)| {
+                    (client_addr.client_connection_id(), subscription_ids.len())
+                })
+                .collect(),
+            per_path,
+        })
+    }
+}
+
+impl SignalManager {
+    ///
+    /// Notify all matching subscriptions of the new signal value and update the signal cache.
+    /// The signal is invalidated if it does not receive another update within its validity.
+    ///
+    pub(crate) fn update_signal(
+        &mut self,
+        path: ActionPath,
+        value: Value,
+        timestamp: u128,
+        ctx: &mut Context<Self>,
+    ) {
+        for subscription_addr in self.subscription_addrs(&path) {
+            subscription_addr.do_send(NotifySubscriber {
+                path: path.clone(),
+                signal_value: value.clone(),
+                timestamp,
+            });
+        }
+
+        if let Some(handle) = self.validity_handles.remove(&path) {
+            ctx.cancel_future(handle);
+        }
+        let validity = self
+            .signal_validity
+            .get(&path)
+            .copied()
+            .or(self.config.signal_validity);
+        if let Some(validity) = validity {
+            let expiring_path = path.clone();
+            let handle = ctx.run_later(validity, move |act, _ctx| {
+                act.validity_handles.remove(&expiring_path);
+                debug!("Validity of signal {} expired", expiring_path);
+                act.invalidate_signal(&expiring_path);
+            });
+            self.validity_handles.insert(path.clone(), handle);
+        }
+
+        debug!("Updating signal cache value for path: {}", path);
+        self.signal_cache.insert(
+            path,
+            CachedSignal {
+                value,
+                timestamp,
+                received: Instant::now(),
+                available: true,
+            },
+        );
+    }
+
+    ///
+    /// Mark the cached signal as unavailable and notify all matching subscriptions.
+    ///
+    pub(crate) fn invalidate_signal(&mut self, path: &ActionPath) {
+        match self.signal_cache.get_mut(path) {
+            Some(cached) if cached.available => cached.available = false,
+            _ => return,
+        }
+
+        debug!("Signal {} is unavailable", path);
+        for subscription_addr in self.subscription_addrs(path) {
+            subscription_addr.do_send(SignalUnavailable { path: path.clone() });
+        }
+    }
+
+    fn subscription_addrs(&self, path: &ActionPath) -> Vec<Addr<Subscription>> {
+        self.subscription_index
+            .matching(path)
+            .into_iter()
+            .filter_map(|subscription_id| {
+                match self.subscription_id_to_subscription.get(&subscription_id) {
+                    None => {
+                        warn!("Missing addr for SubscriptionId {}", subscription_id);
+                        None
+                    }
+                    Some((subscription_addr, _client_session_addr, _path)) => {
+                        Some(subscription_addr.clone())
+                    }
+                }
+            })
+            .collect()
     }
 }
 
 /// A client subscription.
 #[derive(Clone)]
 pub struct Subscription {
-    /// Client who send subscribe request
-    pub client_addr: Addr<ClientSession>,
+    /// Client who send subscribe request, `None` while the client session is waiting to be resumed
+    pub client_addr: Option<ClientAddr>,
 
-    /// Signal path
+    /// Subscribed signal path, may be a branch or contain wildcards
     pub path: ActionPath,
 
     /// A random subscriptionId that is generated when creating a subscription.
@@ -92,33 +522,73 @@ pub struct Subscription {
     /// Filters e.g. minChange requested by client when subscribing
     pub filters: Option<Filters>,
 
-    /// Latest known signal value, this may not have been sent to the client yet
-    /// if the filter did not match or if this an interval based subscription.
-    pub latest_signal_value: Option<Value>,
+    /// Latest known signal value per signal path and the timestamp it was measured,
+    /// this may not have been sent to the client yet if the filter did not match or if this an interval based subscription.
+    pub latest_signal_value: HashMap<ActionPath, (u128, Value)>,
 
-    /// Last value send to client via SubscriptionNotification, contains timestamp when last value was sent
-    pub last_signal_value_client: Option<(SystemTime, Value)>,
+    /// Last value per signal path send to client via SubscriptionNotification,
+    /// contains timestamp when last value was sent
+    pub last_signal_value_client: HashMap<ActionPath, (SystemTime, Value)>,
 
     /// Handle used when the subscription contains an interval filter
     pub interval_handle: Option<SpawnHandle>,
+
+    /// Signal paths with updates that have not been sent yet, when the subscription contains a coalesce filter
+    pub coalesce_pending: HashSet<ActionPath>,
+
+    /// Handle of the scheduled delivery of coalesced updates
+    pub coalesce_handle: Option<SpawnHandle>,
+
+    /// Signal paths for which the client was notified that its values are invalid for the filters
+    pub invalid_filter_paths: HashSet<ActionPath>,
+
+    /// Filter state per signal path e.g. for the range hysteresis
+    pub filter_states: HashMap<ActionPath, filter::FilterState>,
+
+    /// Sequence number of the last notification sent to the client
+    pub sequence: u64,
 }
 
 impl Subscription {
-    pub fn send_client_notification(&mut self, signal_value: &Value) {
-        match filter::matches(signal_value, &self.last_signal_value_client, &self.filters) {
+    pub fn send_client_notification(
+        &mut self,
+        path: &ActionPath,
+        signal_value: &Value,
+        signal_timestamp: u128,
+    ) {
+        // Updates are compared to the last value the client received once it is resumed
+        if self.client_addr.is_none() {
+            return;
+        }
+
+        let last_signal_value_client = self.last_signal_value_client.get(path).cloned();
+        let filter_state = self.filter_states.entry(path.clone()).or_default();
+        match filter::matches(
+            signal_value,
+            &last_signal_value_client,
+            &self.filters,
+            filter_state,
+        ) {
             Ok(true) => {
                 debug!(
                     "Notifiying SubscriptionId {} of value change",
                     self.subscription_id
                 );
 
-                self.last_signal_value_client = Some((SystemTime::now(), signal_value.clone()));
-                let s = ActionSuccessResponse::Subscription {
-                    subscription_id: self.subscription_id,
-                    value: signal_value.clone(),
-                    timestamp: unix_timestamp_ms(),
+                let deep_diff = self
+                    .filters
+                    .as_ref()
+                    .and_then(|filters| filters.deep_diff)
+                    .unwrap_or(false);
+                let value = match last_signal_value_client {
+                    Some((_time, ref last_value)) if deep_diff => {
+                        filter::deep_diff(last_value, signal_value)
+                    }
+                    _ => signal_value.clone(),
                 };
-                self.client_addr.do_send(s);
+
+                self.invalid_filter_paths.remove(path);
+                self.send_value(path, value, signal_value, signal_timestamp);
             }
             // Value is filtered and will not be send to client
             Ok(false) => {
@@ -127,14 +597,66 @@ impl Subscription {
                     self.subscription_id
                 );
             }
-            Err(filter::Error::ValueIsNotANumber) => {
+            // Only reported once, until the signal matches the filter again
+            Err(e) if self.invalid_filter_paths.insert(path.clone()) => {
                 let s = ActionErrorResponse::SubscriptionNotification {
                     subscription_id: self.subscription_id,
-                    error: BAD_REQUEST_FILTER_INVALID.into(),
+                    error: BAD_REQUEST_FILTER_INVALID
+                        .with_message(format!("{}, signal {}", e, path)),
                     timestamp: unix_timestamp_ms(),
                 };
-                self.client_addr.do_send(s);
+                self.send(s);
             }
+            Err(_) => {}
+        }
+    }
+
+    /// Send a notification with the given value, which is the signal value or a diff to the last value.
+    fn send_value(
+        &mut self,
+        path: &ActionPath,
+        value: Value,
+        signal_value: &Value,
+        signal_timestamp: u128,
+    ) {
+        self.last_signal_value_client
+            .insert(path.clone(), (SystemTime::now(), signal_value.clone()));
+        self.sequence += 1;
+        let data_point = DataPoint {
+            path: path.clone(),
+            value: value.clone(),
+            timestamp: signal_timestamp,
+        };
+        let s = ActionSuccessResponse::Subscription {
+            subscription_id: self.subscription_id,
+            // Branch and wildcard subscriptions need to know which signal changed
+            path: if *path == self.path {
+                None
+            } else {
+                Some(path.clone())
+            },
+            value,
+            sequence: Some(self.sequence),
+            timestamp: signal_timestamp,
+        };
+        self.send(Response::from(s).with_data(vec![data_point]));
+    }
+
+    /// Send the latest value of each signal regardless of the filters, the filter state is based on these values.
+    pub fn send_latest_values(&mut self) {
+        for (path, (timestamp, value)) in self.latest_signal_value.clone() {
+            let filter_state = self.filter_states.entry(path.clone()).or_default();
+            filter::seed(&value, &self.filters, filter_state);
+            self.send_value(&path, value.clone(), &value, timestamp);
+        }
+    }
+
+    fn send<M>(&self, msg: M)
+    where
+        M: Into<Response>,
+    {
+        if let Some(ref client_addr) = self.client_addr {
+            client_addr.do_send(msg);
         }
     }
 }
@@ -153,7 +675,10 @@ impl PartialEq<Subscription> for Subscription {
 
 #[derive(Debug, Clone)]
 pub struct NotifySubscriber {
+    pub path: ActionPath,
     pub signal_value: Value,
+    /// Unix timestamp in milliseconds when the value was measured
+    pub timestamp: u128,
 }
 
 impl Message for NotifySubscriber {
@@ -175,10 +700,9 @@ impl Actor for Subscription {
                     debug!("Starting subscription interval {}", interval);
 
                     Some(
-                        ctx.run_interval(std::time::Duration::from_secs(interval), |act, _ctx| {
-                            if let Some(ref latest_signal_value) = act.latest_signal_value {
-                                let value = latest_signal_value.clone();
-                                act.send_client_notification(&value);
+                        ctx.run_interval(Duration::from_millis(interval), |act, _ctx| {
+                            for (path, (timestamp, value)) in act.latest_signal_value.clone() {
+                                act.send_client_notification(&path, &value, timestamp);
                             }
                         }),
                     )
@@ -198,18 +722,104 @@ impl Actor for Subscription {
 impl Handler<NotifySubscriber> for Subscription {
     type Result = ();
 
-    fn handle(&mut self, msg: NotifySubscriber, _ctx: &mut Self::Context) {
-        self.latest_signal_value = Some(msg.signal_value.clone());
+    fn handle(&mut self, msg: NotifySubscriber, ctx: &mut Self::Context) {
+        self.latest_signal_value
+            .insert(msg.path.clone(), (msg.timestamp, msg.signal_value.clone()));
 
-        // Interval based subscriptions are handled in the timer
-        if self
+        let (interval, coalesce) = self
             .filters
             .as_ref()
-            .map(|ref x| x.interval.is_none())
-            .unwrap_or(true)
-        {
-            debug!("{:#?}", self.filters);
-            self.send_client_notification(&msg.signal_value);
+            .map(|filters| (filters.interval, filters.coalesce))
+            .unwrap_or((None, None));
+
+        // Interval based subscriptions are only notified by the timer, which enforces the interval
+        if interval.is_some() {
+            return;
+        }
+
+        match coalesce {
+            Some(coalesce) => {
+                self.coalesce_pending.insert(msg.path);
+                if self.coalesce_handle.is_none() {
+                    let handle = ctx.run_later(Duration::from_millis(coalesce), |act, _ctx| {
+                        act.coalesce_handle = None;
+                        act.send_coalesced_notifications();
+                    });
+                    self.coalesce_handle = Some(handle);
+                }
+            }
+            None => {
+                debug!("{:#?}", self.filters);
+                self.send_client_notification(&msg.path, &msg.signal_value, msg.timestamp);
+            }
+        }
+    }
+}
+
+impl Subscription {
+    /// Send the newest value of each signal that was updated since the last coalesced delivery.
+    fn send_coalesced_notifications(&mut self) {
+        let pending: Vec<ActionPath> = self.coalesce_pending.drain().collect();
+        for path in pending {
+            if let Some((timestamp, value)) = self.latest_signal_value.get(&path).cloned() {
+                self.send_client_notification(&path, &value, timestamp);
+            }
+        }
+    }
+}
+
+///
+/// A signal matching the subscription became unavailable.
+///
+#[derive(Debug, Clone)]
+pub struct SignalUnavailable {
+    pub path: ActionPath,
+}
+
+impl Message for SignalUnavailable {
+    type Result = ();
+}
+
+impl Handler<SignalUnavailable> for Subscription {
+    type Result = ();
+
+    fn handle(&mut self, msg: SignalUnavailable, _ctx: &mut Self::Context) {
+        // Once the signal is available again, the next value is not compared to values from before
+        self.latest_signal_value.remove(&msg.path);
+        self.last_signal_value_client.remove(&msg.path);
+        self.filter_states.remove(&msg.path);
+
+        let s = ActionErrorResponse::SubscriptionNotification {
+            subscription_id: self.subscription_id,
+            error: NOT_FOUND_UNAVAILABLE_DATA
+                .with_message(format!("Signal {} is currently not available.", msg.path)),
+            timestamp: unix_timestamp_ms(),
+        };
+        self.send(s);
+    }
+}
+
+///
+/// Move the subscription to a resumed client session, or detach it from a disconnected session with `None`.
+///
+#[derive(Clone)]
+pub struct AttachClient {
+    pub client_addr: Option<ClientAddr>,
+    /// Send the latest value of each signal, regardless of the filters
+    pub replay: bool,
+}
+
+impl Message for AttachClient {
+    type Result = ();
+}
+
+impl Handler<AttachClient> for Subscription {
+    type Result = ();
+
+    fn handle(&mut self, msg: AttachClient, _ctx: &mut Self::Context) {
+        self.client_addr = msg.client_addr;
+        if msg.replay {
+            self.send_latest_values();
         }
     }
 }
@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! Subscriptions over Server-Sent Events, for clients without a websocket library.
+//! `GET /subscribe?path=Vehicle.Speed&interval=1000` creates a subscription and streams its
+//! notifications as events. The subscription is removed when the connection closes.
+//! Each event contains a `subscription` notification in the format of websocket subscriptions.
+//! `filters` takes the URL encoded JSON `filters` of a websocket subscribe request,
+//! `interval` is a shortcut for `filters={"interval":1000}`.
+//!
+
+use actix::clock::{interval_at, Duration, Instant, Interval};
+use actix::prelude::*;
+use actix_web::http::header::CACHE_CONTROL;
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures::task::{Context, Poll};
+use serde_json::from_str;
+use std::pin::Pin;
+use uuid::Uuid;
+
+use crate::action;
+use crate::action::ClientMessage;
+use crate::api_error::{new_deserialization_error, new_subscribe_error};
+use crate::api_type::{Filters, ReqID};
+use crate::codec::Codec;
+use crate::http_api::{action_path, authorization, error_response, http_response};
+use crate::outbound::{Outbound, Outbox};
+use crate::router::{AppState, ClientAddr};
+use crate::signal_manager::SignalManager;
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::outbound::SlowConsumerPolicy;
+    use crate::signal_manager::UpdateSignal;
+    use crate::sse::*;
+    use actix::clock::delay_for;
+    use futures::task::noop_waker;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn subscribe_query() {
+        let query = web::Query::<SubscribeQuery>::from_query(
+            "path=Vehicle/Speed&interval=1000&initialValue=true",
+        )
+        .unwrap();
+        assert_eq!("Vehicle/Speed", query.path);
+        assert_eq!(Some(1000), query.interval);
+        assert_eq!(Some(true), query.initial_value);
+        assert!(web::Query::<SubscribeQuery>::from_query("interval=1000").is_err());
+    }
+
+    #[test]
+    fn subscribe_query_filters() {
+        let query = |query: &str| {
+            web::Query::<SubscribeQuery>::from_query(query)
+                .unwrap()
+                .filters()
+        };
+        let filters =
+            query("path=Vehicle.Speed&filters=%7B%22minChange%22%3A5%2C%22coalesce%22%3A100%7D")
+                .unwrap()
+                .unwrap();
+        assert_eq!(Some(json!(5)), filters.min_change.map(Value::Number));
+        assert_eq!(Some(100), filters.coalesce);
+        let filters = query("path=Vehicle.Speed&interval=1000").unwrap().unwrap();
+        assert_eq!(Some(1000), filters.interval);
+        assert!(query("path=Vehicle.Speed").unwrap().is_none());
+        assert!(query("path=Vehicle.Speed&filters=%7B").is_err());
+        assert!(query("path=Vehicle.Speed&interval=1000&filters=%7B%7D").is_err());
+    }
+
+    fn client_message<M>(client_addr: &ClientAddr, message: M) -> ClientMessage<M> {
+        ClientMessage {
+            client_connection_id: client_addr.client_connection_id(),
+            client_addr: client_addr.clone(),
+            authorization: None,
+            message,
+        }
+    }
+
+    fn update(signal_manager: &Addr<SignalManager>, value: i64) {
+        signal_manager.do_send(UpdateSignal {
+            path: "Vehicle.Speed".into(),
+            value: json!(value),
+            timestamp: None,
+        });
+    }
+
+    #[test]
+    fn drop_event_stream_removes_subscription() {
+        System::new("sse").block_on(async {
+            let signal_manager = SignalManager::new(Config::default()).start();
+            let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+            let client_addr = ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::Legacy);
+            signal_manager.do_send(client_message(
+                &client_addr,
+                action::Subscribe {
+                    path: "Vehicle.Speed".into(),
+                    request_id: ReqID::default(),
+                    filters: None,
+                    initial_value: None,
+                },
+            ));
+            assert!(outbox.recv().await.unwrap().status().is_success());
+
+            let events = EventStream::new(
+                outbox.clone(),
+                client_addr,
+                signal_manager.clone(),
+                Duration::from_secs(15),
+            );
+            update(&signal_manager, 1);
+            assert!(outbox.recv().await.is_some());
+
+            drop(events);
+            update(&signal_manager, 2);
+            delay_for(Duration::from_millis(50)).await;
+            assert!(outbox.lock().pop().is_none());
+        });
+    }
+
+    #[test]
+    fn event_stream_without_keep_alive() {
+        System::new("sse").block_on(async {
+            let signal_manager = SignalManager::new(Config::default()).start();
+            let outbox = Outbox::new(1024, SlowConsumerPolicy::DropOldest, Default::default());
+            let client_addr = ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::Legacy);
+            let mut events =
+                EventStream::new(outbox, client_addr, signal_manager, Duration::from_secs(0));
+            let waker = noop_waker();
+            let mut cx = Context::from_waker(&waker);
+            assert!(Pin::new(&mut events).poll_next(&mut cx).is_pending());
+        });
+    }
+
+    #[test]
+    fn event_data() {
+        let outbound: Outbound = r#"{"action":"subscription"}"#.to_string().into();
+        assert_eq!(
+            Bytes::from_static(b"data: {\"action\":\"subscription\"}\n\n"),
+            event(outbound)
+        );
+    }
+}
+
+#[derive(Deserialize)]
+struct SubscribeQuery {
+    path: String,
+    interval: Option<u64>,
+    /// JSON `Filters`
+    filters: Option<String>,
+    #[serde(rename = "initialValue")]
+    initial_value: Option<bool>,
+}
+
+impl SubscribeQuery {
+    fn filters(&self) -> Result<Option<Filters>, String> {
+        match (&self.filters, self.interval) {
+            (Some(_), Some(_)) => Err("`interval` can not be combined with `filters`".to_string()),
+            (Some(filters), None) => from_str(filters).map(Some).map_err(|e| e.to_string()),
+            (None, interval) => Ok(interval.map(|interval| Filters {
+                interval: Some(interval),
+                ..Default::default()
+            })),
+        }
+    }
+}
+
+/// `GET /subscribe?path={path}&interval={interval}&filters={filters}&initialValue={initialValue}`
+pub(crate) async fn subscribe(state: web::Data<AppState>, r: HttpRequest) -> HttpResponse {
+    let request_id = ReqID::default();
+    let query = web::Query::<SubscribeQuery>::from_query(r.query_string())
+        .map_err(|e| e.to_string())
+        .and_then(|query| Ok((query.filters()?, query.into_inner())));
+    let (filters, query) = match query {
+        Ok(query) => query,
+        Err(e) => {
+            debug!("Rejecting Server-Sent Events subscribe query: {}", e);
+            return error_response(
+                Codec::Legacy,
+                new_subscribe_error(request_id, new_deserialization_error()),
+            );
+        }
+    };
+    let authorization = match authorization(&state, &r, request_id) {
+        Ok(authorization) => authorization,
+        Err(error) => return error_response(Codec::Legacy, error),
+    };
+
+    let outbox = state.new_outbox();
+    let client_addr = ClientAddr::new(Uuid::new_v4(), outbox.clone(), Codec::Legacy);
+    state.signal_manager_addr().do_send(ClientMessage {
+        client_connection_id: client_addr.client_connection_id(),
+        client_addr: client_addr.clone(),
+        authorization,
+        message: action::Subscribe {
+            path: action_path(&query.path),
+            request_id,
+            filters,
+            initial_value: query.initial_value,
+        },
+    });
+
+    // Dropping the stream removes the subscription, also if the subscribe request failed
+    let events = EventStream::new(
+        outbox,
+        client_addr,
+        state.signal_manager_addr(),
+        state.config().sse_keep_alive,
+    );
+
+    // The subscribe response decides the status of the HTTP response
+    match events.outbox.recv().await {
+        Some(response) if response.status().is_success() => HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            .streaming(events),
+        Some(error) => http_response(error),
+        None => HttpResponse::ServiceUnavailable().finish(),
+    }
+}
+
+/// Serialized messages do not contain line breaks and fit into a single `data` field.
+fn event(outbound: Outbound) -> Bytes {
+    Bytes::from(format!("data: {}\n\n", outbound.into_text()))
+}
+
+///
+/// Response body of a Server-Sent Events subscription.
+/// Writes the notifications of the subscription and keep-alive comments while idle.
+///
+struct EventStream {
+    outbox: Outbox,
+    client_addr: ClientAddr,
+    signal_manager_addr: Addr<SignalManager>,
+    /// `None` if keep-alive comments are disabled
+    keep_alive: Option<Interval>,
+}
+
+impl EventStream {
+    fn new(
+        outbox: Outbox,
+        client_addr: ClientAddr,
+        signal_manager_addr: Addr<SignalManager>,
+        keep_alive: Duration,
+    ) -> Self {
+        Self {
+            outbox,
+            client_addr,
+            signal_manager_addr,
+            // A zero period would make the interval panic
+            keep_alive: if keep_alive == Duration::from_secs(0) {
+                None
+            } else {
+                Some(interval_at(Instant::now() + keep_alive, keep_alive))
+            },
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<Bytes, actix_web::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match this.outbox.poll_recv(cx) {
+            Poll::Ready(Some(outbound)) => return Poll::Ready(Some(Ok(event(outbound)))),
+            // Closed by the `SlowConsumerPolicy::Disconnect` policy
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => {}
+        }
+
+        match this
+            .keep_alive
+            .as_mut()
+            .map(|keep_alive| keep_alive.poll_tick(cx))
+        {
+            Some(Poll::Ready(_)) => Poll::Ready(Some(Ok(Bytes::from_static(b": keep-alive\n\n")))),
+            Some(Poll::Pending) | None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        // Cleanup the subscription like a disconnected websocket session
+        self.signal_manager_addr.do_send(ClientMessage {
+            client_connection_id: self.client_addr.client_connection_id(),
+            client_addr: self.client_addr.clone(),
+            authorization: None,
+            message: action::UnsubscribeAll { request_id: None },
+        });
+    }
+}
@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! Index of subscriptions by subscribed path, organized as a trie of path segments.
+//! Looking up the subscriptions of an updated signal only visits the segments of the signal path
+//! and the `*` wildcard branches along it, instead of comparing against every subscribed path.
+//!
+
+use std::collections::HashMap;
+
+use crate::api_type::{ActionPath, SubscriptionID, WILDCARD};
+
+#[cfg(test)]
+mod tests {
+    use crate::api_type::SubscriptionID;
+    use crate::subscription_index::SubscriptionIndex;
+
+    fn ids(index: &SubscriptionIndex, path: &str) -> Vec<SubscriptionID> {
+        let mut ids = index.matching(&path.into());
+        ids.sort_by_key(|id| id.to_string());
+        ids
+    }
+
+    #[test]
+    fn matching_exact_path() {
+        let mut index = SubscriptionIndex::default();
+        let id = SubscriptionID::SubscriptionIDInt(1);
+        index.insert(&"Signal.Body.Trunk.IsOpen".into(), id);
+        assert_eq!(vec![id], ids(&index, "signal.body.trunk.isopen"));
+        assert!(ids(&index, "Signal.Body.Trunk").is_empty());
+    }
+
+    #[test]
+    fn matching_branch_and_wildcard_paths() {
+        let mut index = SubscriptionIndex::default();
+        let branch = SubscriptionID::SubscriptionIDInt(1);
+        let wildcard = SubscriptionID::SubscriptionIDInt(2);
+        let inner_wildcard = SubscriptionID::SubscriptionIDInt(3);
+        index.insert(&"Signal.Body".into(), branch);
+        index.insert(&"Signal.Body.*".into(), wildcard);
+        index.insert(&"Signal.*.Trunk.IsOpen".into(), inner_wildcard);
+
+        assert_eq!(
+            vec![branch, wildcard, inner_wildcard],
+            ids(&index, "Signal.Body.Trunk.IsOpen")
+        );
+        assert_eq!(vec![branch], ids(&index, "Signal.Body"));
+        assert!(ids(&index, "Signal.Cabin.Light").is_empty());
+    }
+
+    #[test]
+    fn count_exact_path() {
+        let mut index = SubscriptionIndex::default();
+        index.insert(&"Signal.Body".into(), SubscriptionID::SubscriptionIDInt(1));
+        index.insert(&"Signal.Body".into(), SubscriptionID::SubscriptionIDInt(2));
+        index.insert(
+            &"Signal.Body.Trunk".into(),
+            SubscriptionID::SubscriptionIDInt(3),
+        );
+        assert_eq!(2, index.count(&"signal.body".into()));
+        assert_eq!(1, index.count(&"Signal.Body.Trunk".into()));
+        assert_eq!(0, index.count(&"Signal".into()));
+        assert_eq!(0, index.count(&"Signal.Cabin".into()));
+    }
+
+    #[test]
+    fn remove_prunes_empty_nodes() {
+        let mut index = SubscriptionIndex::default();
+        let id = SubscriptionID::SubscriptionIDInt(1);
+        let path = "Signal.Body.*".into();
+        index.insert(&path, id);
+        index.remove(&path, &id);
+        assert!(ids(&index, "Signal.Body.Trunk").is_empty());
+        assert!(index.root.is_empty());
+    }
+}
+
+#[derive(Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    subscription_ids: Vec<SubscriptionID>,
+}
+
+impl Node {
+    fn is_empty(&self) -> bool {
+        self.children.is_empty() && self.subscription_ids.is_empty()
+    }
+
+    fn remove(&mut self, segments: &[String], subscription_id: &SubscriptionID) {
+        match segments.split_first() {
+            None => self.subscription_ids.retain(|id| id != subscription_id),
+            Some((segment, rest)) => {
+                if let Some(child) = self.children.get_mut(segment) {
+                    child.remove(rest, subscription_id);
+                    if child.is_empty() {
+                        self.children.remove(segment);
+                    }
+                }
+            }
+        }
+    }
+
+    fn collect(&self, segments: &[String], subscription_ids: &mut Vec<SubscriptionID>) {
+        // Subscriptions ending at this node subscribed to this path or a branch above it.
+        subscription_ids.extend(self.subscription_ids.iter().copied());
+
+        if let Some((segment, rest)) = segments.split_first() {
+            if let Some(child) = self.children.get(segment) {
+                child.collect(rest, subscription_ids);
+            }
+            if let Some(child) = self.children.get(WILDCARD) {
+                child.collect(rest, subscription_ids);
+            }
+        }
+    }
+}
+
+///
+/// Subscriptions by subscribed path, a subscription matches its path and all paths below,
+/// see `ActionPath::matches`.
+///
+#[derive(Default)]
+pub struct SubscriptionIndex {
+    root: Node,
+}
+
+impl SubscriptionIndex {
+    pub fn insert(&mut self, path: &ActionPath, subscription_id: SubscriptionID) {
+        let node = segments(path)
+            .into_iter()
+            .fold(&mut self.root, |node, segment| {
+                node.children.entry(segment).or_default()
+            });
+        node.subscription_ids.push(subscription_id);
+    }
+
+    pub fn remove(&mut self, path: &ActionPath, subscription_id: &SubscriptionID) {
+        self.root.remove(&segments(path), subscription_id);
+    }
+
+    /// Number of subscriptions to exactly this path.
+    pub fn count(&self, path: &ActionPath) -> usize {
+        segments(path)
+            .iter()
+            .try_fold(&self.root, |node, segment| node.children.get(segment))
+            .map(|node| node.subscription_ids.len())
+            .unwrap_or_default()
+    }
+
+    /// Subscriptions that match the signal path.
+    pub fn matching(&self, path: &ActionPath) -> Vec<SubscriptionID> {
+        let mut subscription_ids = Vec::new();
+        self.root.collect(&segments(path), &mut subscription_ids);
+        subscription_ids
+    }
+}
+
+/// Paths are case insensitive, see `ActionPath`
+fn segments(path: &ActionPath) -> Vec<String> {
+    path.segments().map(str::to_lowercase).collect()
+}
@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! TLS and mutual TLS for the websocket and HTTP routes, enabled by the `tls` feature.
+//! Bind the server with the acceptor of a `TlsConfig` and register `on_connect` to pass
+//! the identity of verified client certificates to the `Authorizer`.
+//!
+//! ```ignore
+//! let tls = TlsConfig {
+//!     certificate_chain: "server.pem".into(),
+//!     private_key: "server.key".into(),
+//!     client_ca: Some("client-ca.pem".into()),
+//! };
+//! HttpServer::new(move || App::new().data(AppState::default()).configure(Router::configure_routes))
+//!     .on_connect(tls::on_connect)
+//!     .bind_openssl("0.0.0.0:14430", tls.acceptor()?)?
+//!     .run()
+//!     .await
+//! ```
+//!
+
+use actix_tls::openssl::SslStream;
+use actix_web::dev::Extensions;
+use actix_web::rt::net::TcpStream;
+use openssl::error::ErrorStack;
+use openssl::nid::Nid;
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod, SslVerifyMode};
+use std::any::Any;
+use std::path::PathBuf;
+
+use crate::action::authorize::ClientIdentity;
+
+#[cfg(test)]
+mod tests {
+    use crate::test_certificate::{certificate, KeyPair};
+    use crate::tls::*;
+    use actix_service::{Service, ServiceFactory};
+    use actix_tls::openssl::Acceptor;
+    use actix_web::rt::net::TcpListener;
+    use actix_web::rt::System;
+    use openssl::ssl::SslConnector;
+    use openssl::x509::X509;
+    use std::fs::{create_dir_all, write};
+    use std::path::Path;
+    use std::thread;
+    use uuid::Uuid;
+
+    /// Server certificate for `localhost` issued by the `ca`, which also verifies client certificates.
+    fn tls_config(dir: &Path, ca: &KeyPair) -> TlsConfig {
+        let (key, server) = certificate("localhost", Some(ca));
+        let config = TlsConfig {
+            certificate_chain: dir.join("server.pem"),
+            private_key: dir.join("server.key"),
+            client_ca: Some(dir.join("ca.pem")),
+        };
+        write(&config.certificate_chain, server.to_pem().unwrap()).unwrap();
+        write(&config.private_key, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+        write(config.client_ca.as_ref().unwrap(), ca.1.to_pem().unwrap()).unwrap();
+        config
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// `ClientIdentity` stored by `on_connect` for a client presenting the `client` certificate.
+    fn handshake(config: &TlsConfig, ca: &X509, client: Option<KeyPair>) -> Option<ClientIdentity> {
+        let acceptor = config.acceptor().unwrap().build();
+        let ca = ca.to_pem().unwrap();
+        System::new("tls").block_on(async move {
+            let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let client = thread::spawn(move || {
+                let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+                connector
+                    .cert_store_mut()
+                    .add_cert(X509::from_pem(&ca).unwrap())
+                    .unwrap();
+                if let Some((key, certificate)) = client {
+                    connector.set_certificate(&certificate).unwrap();
+                    connector.set_private_key(&key).unwrap();
+                }
+                let stream = std::net::TcpStream::connect(addr).unwrap();
+                // Rejected client certificates are checked on the server side
+                let _ = connector.build().connect("localhost", stream);
+            });
+
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut acceptor = Acceptor::new(acceptor).new_service(()).await.unwrap();
+            let identity = match acceptor.call(stream).await {
+                Ok(stream) => {
+                    let mut data = Extensions::new();
+                    on_connect(&stream, &mut data);
+                    data.remove::<ClientIdentity>()
+                }
+                Err(_) => None,
+            };
+            client.join().unwrap();
+            identity
+        })
+    }
+
+    #[test]
+    fn acceptor_requires_client_certificate() {
+        let ca = certificate("Test CA", None);
+        let mut config = tls_config(&temp_dir(), &ca);
+        let acceptor = config.acceptor().unwrap().build();
+        assert_eq!(
+            SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+            acceptor.context().verify_mode()
+        );
+
+        config.client_ca = None;
+        let acceptor = config.acceptor().unwrap().build();
+        assert_eq!(SslVerifyMode::NONE, acceptor.context().verify_mode());
+    }
+
+    #[test]
+    fn acceptor_err_when_key_does_not_match() {
+        let ca = certificate("Test CA", None);
+        let config = tls_config(&temp_dir(), &ca);
+        let (other_key, _) = certificate("other", Some(&ca));
+        write(
+            &config.private_key,
+            other_key.private_key_to_pem_pkcs8().unwrap(),
+        )
+        .unwrap();
+        assert!(config.acceptor().is_err());
+    }
+
+    #[test]
+    fn acceptor_err_when_file_missing() {
+        let ca = certificate("Test CA", None);
+        let mut config = tls_config(&temp_dir(), &ca);
+        config.client_ca = Some(temp_dir().join("missing.pem"));
+        assert!(config.acceptor().is_err());
+    }
+
+    #[test]
+    fn client_identity_of_verified_certificate() {
+        let ca = certificate("Test CA", None);
+        let config = tls_config(&temp_dir(), &ca);
+        let client = certificate("vehicle-client", Some(&ca));
+        assert_eq!(
+            Some(ClientIdentity {
+                common_name: "vehicle-client".to_string()
+            }),
+            handshake(&config, &ca.1, Some(client))
+        );
+    }
+
+    #[test]
+    fn no_client_identity_without_verified_certificate() {
+        let ca = certificate("Test CA", None);
+        let config = tls_config(&temp_dir(), &ca);
+        assert_eq!(None, handshake(&config, &ca.1, None));
+
+        let other_ca = certificate("Other CA", None);
+        let client = certificate("vehicle-client", Some(&other_ca));
+        assert_eq!(None, handshake(&config, &ca.1, Some(client)));
+    }
+}
+
+///
+/// Certificates and key of the server.
+///
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    /// PEM file with the server certificate, followed by its intermediate certificates
+    pub certificate_chain: PathBuf,
+    /// PEM file with the private key of the server certificate
+    pub private_key: PathBuf,
+    /// PEM file with the CA certificates client certificates are verified against,
+    /// `None` if clients do not authenticate with a certificate
+    pub client_ca: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Acceptor for `HttpServer::bind_openssl`, requiring a client certificate if `client_ca` is set.
+    pub fn acceptor(&self) -> Result<SslAcceptorBuilder, ErrorStack> {
+        let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+        builder.set_certificate_chain_file(&self.certificate_chain)?;
+        builder.set_private_key_file(&self.private_key, SslFiletype::PEM)?;
+        builder.check_private_key()?;
+
+        if let Some(ref client_ca) = self.client_ca {
+            builder.set_ca_file(client_ca)?;
+            builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        }
+        Ok(builder)
+    }
+}
+
+///
+/// Connection callback for `HttpServer::on_connect`.
+/// Stores the `ClientIdentity` of a verified client certificate for the requests of the connection.
+///
+pub fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+    if let Some(identity) = connection
+        .downcast_ref::<SslStream<TcpStream>>()
+        .and_then(client_identity)
+    {
+        data.insert(identity);
+    }
+}
+
+fn client_identity(stream: &SslStream<TcpStream>) -> Option<ClientIdentity> {
+    let certificate = stream.ssl().peer_certificate()?;
+    // Raw bytes of the common name, names that are not UTF-8 encoded are not accepted
+    let common_name = certificate
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()?
+        .data()
+        .as_slice()
+        .to_vec();
+    let common_name = String::from_utf8(common_name).ok()?;
+    Some(ClientIdentity { common_name })
+}
@@ -0,0 +1,286 @@
+// SPDX-License-Identifier: MIT
+
+//!
+//! Websocket and HTTP routes on a Unix domain socket for local-only clients.
+//! Access is restricted by the file mode of the socket, websocket sessions and HTTP requests are
+//! authorized by the user and group of the connected process when `on_connect` is registered.
+//!
+//! ```ignore
+//! let uds = UdsConfig {
+//!     path: "/run/vis/vis.sock".into(),
+//!     mode: Some(0o660),
+//! };
+//! HttpServer::new(move || App::new().data(AppState::default()).configure(Router::configure_routes))
+//!     .on_connect(uds::on_connect)
+//!     .listen_uds(uds.listener()?)?
+//!     .run()
+//!     .await
+//! ```
+//!
+
+use actix_web::dev::Extensions;
+use actix_web::rt::net::UnixStream;
+use std::any::Any;
+use std::fs::{hard_link, remove_dir_all, set_permissions, DirBuilder, Permissions};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::action::PeerCredentials;
+
+#[cfg(test)]
+mod tests {
+    use crate::access_control::{Access, AccessControl};
+    use crate::action::authorize::{Authorizer, Grant, Tokens};
+    use crate::api_error::KnownError;
+    use crate::router::{AppState, Router};
+    use crate::uds::*;
+    use actix_web::rt::System;
+    use actix_web::{web, App, HttpServer};
+    use std::fs::{metadata, remove_file, write};
+    use std::io::{Read, Write};
+    use std::os::unix::fs::MetadataExt;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use websocket::{ClientBuilder, OwnedMessage};
+
+    fn socket_path() -> PathBuf {
+        std::env::temp_dir().join(format!("vis-{}.sock", Uuid::new_v4()))
+    }
+
+    /// Grants every request and records the peer credentials passed to it.
+    struct RecordingAuthorizer(Arc<Mutex<Vec<PeerCredentials>>>);
+
+    impl Authorizer for RecordingAuthorizer {
+        fn authorize(&self, tokens: &Tokens) -> Result<Grant, KnownError> {
+            self.0.lock().unwrap().extend(tokens.peer_credentials);
+            Ok(Grant {
+                scope: "local".to_string(),
+                ttl: Duration::from_secs(60),
+            })
+        }
+    }
+
+    #[test]
+    fn listener_sets_mode() {
+        let path = socket_path();
+        let uds = UdsConfig {
+            path: path.clone(),
+            mode: Some(0o600),
+        };
+        let _listener = uds.listener().unwrap();
+        assert_eq!(0o600, metadata(&path).unwrap().permissions().mode() & 0o777);
+        assert!(std::os::unix::net::UnixStream::connect(&path).is_ok());
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn listener_err_when_stale_socket() {
+        let path = socket_path();
+        let uds = UdsConfig {
+            path: path.clone(),
+            mode: None,
+        };
+        drop(uds.listener().unwrap());
+        let error = uds.listener().unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, error.kind());
+        remove_file(&path).unwrap();
+        assert!(uds.listener().is_ok());
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn listener_err_when_server_listening() {
+        let path = socket_path();
+        let uds = UdsConfig {
+            path: path.clone(),
+            mode: None,
+        };
+        let _listener = uds.listener().unwrap();
+        let error = uds.listener().unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, error.kind());
+        assert!(std::os::unix::net::UnixStream::connect(&path).is_ok());
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn listener_err_when_not_socket() {
+        let path = socket_path();
+        write(&path, "data").unwrap();
+        let uds = UdsConfig {
+            path: path.clone(),
+            mode: None,
+        };
+        assert!(uds.listener().is_err());
+        assert_eq!("data", std::fs::read_to_string(&path).unwrap());
+        remove_file(&path).unwrap();
+    }
+
+    ///
+    /// Serve `Vehicle.Speed` to clients authorized for the `local` scope on a new socket and send a single
+    /// `request`. Returns the response and the user ids of the peer credentials passed to the `Authorizer`.
+    ///
+    fn serve<F>(request: F) -> (String, Vec<u32>)
+    where
+        F: FnOnce(&Path) -> io::Result<String> + Send + 'static,
+    {
+        let path = socket_path();
+        let listener = UdsConfig {
+            path: path.clone(),
+            mode: Some(0o600),
+        }
+        .listener()
+        .unwrap();
+        let credentials = Arc::new(Mutex::new(Vec::new()));
+        let recorded = credentials.clone();
+        let request_path = path.clone();
+
+        let response = System::new("uds").block_on(async move {
+            let server = HttpServer::new(move || {
+                let mut state = AppState::default();
+                state.set_authorizer(RecordingAuthorizer(recorded.clone()));
+                state.set_access_control(AccessControl::default().allow(
+                    "local",
+                    "Vehicle.Speed".into(),
+                    Access::Read,
+                ));
+                state.set_signal("Vehicle.Speed".into(), 42);
+                App::new()
+                    .data(state)
+                    .configure(Router::configure_routes)
+                    .configure(Router::configure_http_routes)
+            })
+            .workers(1)
+            .on_connect(on_connect)
+            .listen_uds(listener)
+            .unwrap()
+            .run();
+
+            let response = web::block(move || request(&request_path)).await.unwrap();
+            server.stop(true).await;
+            response
+        });
+        remove_file(&path).unwrap();
+
+        let uids = credentials.lock().unwrap().iter().map(|c| c.uid).collect();
+        (response, uids)
+    }
+
+    fn process_uid() -> u32 {
+        // A new file is owned by the user of the test process, which is also the peer
+        let path = socket_path();
+        write(&path, "").unwrap();
+        let uid = metadata(&path).unwrap().uid();
+        remove_file(&path).unwrap();
+        uid
+    }
+
+    #[test]
+    fn peer_credentials_authorize_http_requests() {
+        let (response, uids) = serve(|path| {
+            let mut stream = std::os::unix::net::UnixStream::connect(path)?;
+            stream.write_all(
+                b"GET /Vehicle/Speed HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            )?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            Ok(response)
+        });
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert_eq!(vec![process_uid()], uids);
+    }
+
+    #[test]
+    fn peer_credentials_authorize_websocket_sessions() {
+        let (response, uids) = serve(|path| {
+            let stream = std::os::unix::net::UnixStream::connect(path)?;
+            let mut client = ClientBuilder::new("ws://localhost/")
+                .unwrap()
+                .connect_on(stream)
+                .unwrap();
+            let get = r#"{ "action": "get", "path": "Vehicle.Speed", "requestId": "1" }"#;
+            client
+                .send_message(&OwnedMessage::Text(get.to_string()))
+                .unwrap();
+            match client.recv_message().unwrap() {
+                OwnedMessage::Text(response) => Ok(response),
+                message => panic!("Unexpected message {:?}", message),
+            }
+        });
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        // Without an AUTHORIZE request
+        assert_eq!(serde_json::json!(42), response["value"], "{}", response);
+        assert_eq!(vec![process_uid()], uids);
+    }
+}
+
+///
+/// Path and permissions of the server socket.
+///
+#[derive(Clone, Debug)]
+pub struct UdsConfig {
+    /// Path of the socket, which must not exist. A socket left by a previous server has to be removed
+    /// before the server is started, the server never replaces an existing file.
+    pub path: PathBuf,
+    /// File mode of the socket, e.g. `0o660` to restrict clients to the user and group of the server,
+    /// `None` to keep the mode of the process umask
+    pub mode: Option<u32>,
+}
+
+impl UdsConfig {
+    /// Listener for `HttpServer::listen_uds`, fails with `AlreadyExists` if the path exists.
+    pub fn listener(&self) -> io::Result<UnixListener> {
+        // Bind inside a directory only the server can access and link the socket to its path once
+        // its mode is set, clients can not connect before the socket is restricted
+        let parent = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let private_dir = parent.join(format!(".vis-{}", Uuid::new_v4()));
+        DirBuilder::new().mode(0o700).create(&private_dir)?;
+        let listener = self.bind_in(&private_dir);
+        remove_dir_all(&private_dir)?;
+        listener
+    }
+
+    fn bind_in(&self, private_dir: &Path) -> io::Result<UnixListener> {
+        let socket = private_dir.join("vis.sock");
+        let listener = UnixListener::bind(&socket)?;
+        if let Some(mode) = self.mode {
+            set_permissions(&socket, Permissions::from_mode(mode))?;
+        }
+        // Unlike a rename, the link fails atomically if the path exists
+        hard_link(&socket, &self.path).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => io::Error::new(
+                e.kind(),
+                format!(
+                    "{} exists, remove the socket of a previous server before starting",
+                    self.path.display()
+                ),
+            ),
+            _ => e,
+        })?;
+        Ok(listener)
+    }
+}
+
+///
+/// Connection callback for `HttpServer::on_connect`.
+/// Stores the `PeerCredentials` of the connected process for the requests of the connection.
+///
+pub fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+    if let Some(stream) = connection.downcast_ref::<UnixStream>() {
+        match stream.peer_cred() {
+            Ok(credentials) => data.insert(PeerCredentials {
+                uid: credentials.uid,
+                gid: credentials.gid,
+            }),
+            Err(e) => warn!(
+                "Failed to read peer credentials of Unix domain socket: {}",
+                e
+            ),
+        }
+    }
+}